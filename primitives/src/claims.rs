@@ -0,0 +1,37 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Polkadot types for the Ethereum claims distribution.
+
+use super::Balance;
+
+/// The state of the claims distribution, as a node reads it each block to update its metrics.
+#[derive(Clone, PartialEq, Eq, Default, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ClaimsMetrics {
+	/// The total of the claims yet to be made.
+	pub outstanding: Balance,
+	/// How many claims have been made.
+	pub claims_count: u32,
+}
+
+decl_runtime_apis! {
+	/// The API for querying the state of the claims distribution.
+	pub trait ClaimsApi {
+		/// Get the current metrics of the distribution.
+		fn claims_metrics() -> ClaimsMetrics;
+	}
+}
@@ -49,6 +49,7 @@ use runtime_primitives::{generic, traits::Extrinsic};
 pub use runtime_primitives::traits::{BlakeTwo256, Hash as HashT};
 
 pub mod parachain;
+pub mod claims;
 
 pub use codec::Compact;
 
@@ -0,0 +1,64 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Benchmarks of the claims module.
+//!
+//! These need a nightly compiler: `cargo +nightly bench -p polkadot-runtime --features bench`.
+
+#![feature(test)]
+
+extern crate test;
+extern crate parity_codec as codec;
+extern crate polkadot_runtime;
+extern crate secp256k1;
+extern crate tiny_keccak;
+
+use codec::{Decode, Encode};
+use polkadot_runtime::claims::{
+	verify_many, EcdsaSignature, RecoveryId, CLAIM_MESSAGE_PREFIX, EIP191_PREFIX,
+	PERSONAL_SIGN_HEADER, PERSONAL_SIGN_VERSION,
+};
+use test::Bencher;
+use tiny_keccak::keccak256;
+
+// The claim message for the encoded account `who`, framed as `personal_sign` frames it.
+fn claim_message(who: &[u8]) -> Vec<u8> {
+	let mut payload = CLAIM_MESSAGE_PREFIX.to_vec();
+	payload.extend_from_slice(who);
+
+	let mut v = vec![EIP191_PREFIX, PERSONAL_SIGN_VERSION];
+	v.extend_from_slice(PERSONAL_SIGN_HEADER);
+	v.extend_from_slice(payload.len().to_string().as_bytes());
+	v.extend(payload);
+	v
+}
+
+fn sign(secret: &secp256k1::SecretKey, message: &[u8]) -> EcdsaSignature {
+	let msg = keccak256(message);
+	let (sig, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&msg), secret).unwrap();
+	let sig: ([u8; 32], [u8; 32]) = Decode::decode(&mut &sig.serialize()[..]).unwrap();
+	(sig.0, sig.1, RecoveryId::new(recovery_id.serialize()).unwrap())
+}
+
+#[bench]
+fn eth_recover(b: &mut Bencher) {
+	let secret = secp256k1::SecretKey::parse(&keccak256(b"Alice")).unwrap();
+	let who = 42u64.encode();
+	let items = vec![(who.clone(), sign(&secret, &claim_message(&who)))];
+	assert!(verify_many(&items)[0].is_some());
+
+	b.iter(|| verify_many(&items));
+}
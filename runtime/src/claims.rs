@@ -17,252 +17,6436 @@
 //! Module to process claims from Ethereum addresses.
 
 use rstd::prelude::*;
+use rstd::result;
+use rstd::marker::PhantomData;
 use tiny_keccak::keccak256;
 use secp256k1;
 use srml_support::{StorageValue, StorageMap};
-use system::ensure_signed;
-use codec::Encode;
-#[cfg(feature = "std")]
-use sr_primitives::traits::Zero;
+use srml_support::dispatch::{Result, Parameter};
+use srml_support::traits::MakePayment;
+use system::{ensure_signed, ensure_inherent};
+use codec::{Encode, Decode, Input, Output};
+use sr_io;
+use substrate_primitives::{H256, H512};
+use sr_primitives::Perbill;
+use sr_primitives::traits::{Zero, One, CheckedAdd, CheckedMul, Verify, Member, SimpleArithmetic, As, MaybeSerializeDebug, StaticLookup};
+use inherents::{ProvideInherent, InherentData, RuntimeString, MakeFatalError, InherentIdentifier};
 use balances;
 
+/// The currency in which claims are paid out.
+pub trait Currency<AccountId> {
+	/// The balance of an account.
+	type Balance: Parameter + Member + SimpleArithmetic + Default + Copy + As<u64> + MaybeSerializeDebug;
+
+	/// The free balance of `who`.
+	fn free_balance(who: &AccountId) -> Self::Balance;
+
+	/// The minimum balance an account may have.
+	fn existential_deposit() -> Self::Balance;
+
+	/// Add `value` to the free balance of `who`, creating the account if it doesn't exist yet.
+	fn deposit_creating(who: &AccountId, value: Self::Balance);
+
+	/// Take `value` out of the free balance of `who`. Fails, changing nothing, if the free
+	/// balance is less than `value`.
+	fn withdraw(who: &AccountId, value: Self::Balance) -> Result;
+}
+
+impl<T: balances::Trait> Currency<T::AccountId> for balances::Module<T> {
+	type Balance = T::Balance;
+
+	fn free_balance(who: &T::AccountId) -> T::Balance {
+		Self::free_balance(who)
+	}
+
+	fn existential_deposit() -> T::Balance {
+		Self::existential_deposit()
+	}
+
+	fn deposit_creating(who: &T::AccountId, value: T::Balance) {
+		Self::increase_free_balance_creating(who, value);
+	}
+
+	fn withdraw(who: &T::AccountId, value: T::Balance) -> Result {
+		let free_balance = Self::free_balance(who);
+		ensure!(free_balance >= value, "Account has too little free balance");
+		Self::set_free_balance(who, free_balance - value);
+		Ok(())
+	}
+}
+
+/// The fee charged for including a transaction.
+pub trait TransactionFee<Balance> {
+	/// The fee of a signed transaction `encoded_len` bytes long.
+	fn fee(encoded_len: usize) -> Balance;
+}
+
+impl<Balance: Zero> TransactionFee<Balance> for () {
+	fn fee(_: usize) -> Balance {
+		Zero::zero()
+	}
+}
+
+impl<T: balances::Trait> TransactionFee<T::Balance> for balances::Module<T> {
+	fn fee(encoded_len: usize) -> T::Balance {
+		// The length is converted up to the balance, which is no narrower than `u64`.
+		Self::transaction_base_fee() + Self::transaction_byte_fee() * As::sa(encoded_len as u64)
+	}
+}
+
+/// The balance type of the currency used by `T`.
+pub type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
 /// Configuration trait.
-pub trait Trait: balances::Trait {
+pub trait Trait: system::Trait {
 	/// The overarching event type.
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	/// The signature a co-signer uses to approve a claim.
+	type CoSignature: Verify<Signer = <Self as system::Trait>::AccountId> + Parameter;
+	/// The currency claims are paid out in.
+	type Currency: Currency<Self::AccountId>;
+	/// The checks run on every claim before it is credited.
+	type Processor: ClaimProcessor<Self::AccountId, BalanceOf<Self>>;
+	/// The rate at which claims in `ForeignClaims` convert into the claims currency.
+	type ForeignRate: RateOracle<BalanceOf<Self>>;
+	/// The fee charged for signed transactions, for `estimate_fee`.
+	type TransactionFee: TransactionFee<BalanceOf<Self>>;
+	/// How the address of a recovered public key is derived.
+	type AddressDerivation: AddressDerivation;
+	/// How the account is hashed in the message of `claim_prehashed`.
+	type PreHashing: PreHashing;
+	/// Where `claim_with_identity` registers the display name of the claimant.
+	type Identity: Identity<Self::AccountId>;
+	/// How `claim_to_location` sends what it claims to another chain.
+	type Teleport: Teleport<BalanceOf<Self>>;
+	/// How `claim_webauthn` verifies the P-256 signatures of passkeys.
+	type WebAuthn: WebAuthnVerifier;
+}
+
+/// A registry of the identities of accounts.
+pub trait Identity<AccountId> {
+	/// Check that `name` may be set as the display name of `who`, without setting it.
+	fn can_set_display_name(who: &AccountId, name: &[u8]) -> Result;
+
+	/// Set `name` as the display name of `who`, once `can_set_display_name` has allowed it.
+	fn set_display_name(who: &AccountId, name: Vec<u8>);
+}
+
+/// No registry, so no display name can be set.
+impl<AccountId> Identity<AccountId> for () {
+	fn can_set_display_name(_: &AccountId, _: &[u8]) -> Result {
+		Err("No identity registry to set the display name in")
+	}
+
+	fn set_display_name(_: &AccountId, _: Vec<u8>) {}
+}
+
+/// A location on another chain, as an XCM `MultiLocation` gives it, which claimed funds may be
+/// sent to.
+#[derive(Clone, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum MultiLocation {
+	/// An account, as the parachain encodes it, on the parachain with the given ID.
+	ParachainAccount(u32, Vec<u8>),
+}
+
+/// A sender of funds to other chains, such as the XCM subsystem.
+pub trait Teleport<Balance> {
+	/// Check that `dest` can be sent to, without sending anything.
+	fn can_teleport(dest: &MultiLocation) -> Result;
+
+	/// Send `amount`, which has been taken out of this chain, to `dest`, once `can_teleport`
+	/// has allowed it.
+	fn teleport(dest: &MultiLocation, amount: Balance);
+}
+
+/// No cross-chain messaging, so nothing can be sent to another chain.
+impl<Balance> Teleport<Balance> for () {
+	fn can_teleport(_: &MultiLocation) -> Result {
+		Err("No cross-chain messaging to teleport with")
+	}
+
+	fn teleport(_: &MultiLocation, _: Balance) {}
+}
+
+/// A verifier of WebAuthn assertions, which passkeys sign with P-256 (secp256r1) keys rather
+/// than the secp256k1 keys of Ethereum.
+pub trait WebAuthnVerifier {
+	/// Whether `signature`, as `r` then `s`, is a valid P-256 signature by `public_key`, as `x`
+	/// then `y`, over `authenticator_data` followed by the SHA-256 hash of `client_data_json`,
+	/// as WebAuthn signs an assertion.
+	fn verify_assertion(
+		public_key: &[u8; 64],
+		authenticator_data: &[u8],
+		client_data_json: &[u8],
+		signature: &[u8; 64]
+	) -> bool;
+}
+
+/// No P-256 verifier, so no assertion is valid.
+impl WebAuthnVerifier for () {
+	fn verify_assertion(_: &[u8; 64], _: &[u8], _: &[u8], _: &[u8; 64]) -> bool {
+		false
+	}
+}
+
+/// A hash of the encoded account, for tooling which signs that rather than the account itself.
+pub trait PreHashing {
+	/// The hash of the encoded account `data`.
+	fn pre_hash(data: &[u8]) -> Vec<u8>;
+}
+
+/// No hashing: the message of `claim_prehashed` is that of `claim`.
+impl PreHashing for () {
+	fn pre_hash(data: &[u8]) -> Vec<u8> {
+		data.to_vec()
+	}
+}
+
+/// Blake2-256, as Substrate tooling hashes with.
+pub struct Blake2PreHashing;
+
+impl PreHashing for Blake2PreHashing {
+	fn pre_hash(data: &[u8]) -> Vec<u8> {
+		sr_io::blake2_256(data).to_vec()
+	}
+}
+
+/// A derivation of the address of an ECDSA public key.
+pub trait AddressDerivation {
+	/// The address of the uncompressed public key `pubkey`, without its `0x04` prefix.
+	fn derive_address(pubkey: &[u8; 64]) -> EthereumAddress;
+}
+
+/// The Ethereum derivation: the last 20 bytes of the Keccak-256 hash of the public key.
+impl AddressDerivation for () {
+	fn derive_address(pubkey: &[u8; 64]) -> EthereumAddress {
+		let mut res = EthereumAddress::default();
+		res.copy_from_slice(&keccak256(&pubkey[..])[12..]);
+		res
+	}
+}
+
+/// A source of the current rate of some foreign unit in the claims currency.
+pub trait RateOracle<Balance> {
+	/// How much of the claims currency one foreign unit is worth, or `None` if there is no
+	/// rate available.
+	fn rate() -> Option<Balance>;
+}
+
+impl<Balance> RateOracle<Balance> for () {
+	fn rate() -> Option<Balance> {
+		None
+	}
+}
+
+/// An Ethereum address (i.e. 20 bytes, used to represent an Ethereum account).
+pub type EthereumAddress = [u8; 20];
+/// An Ethereum ECDSA signature as `(r, s, v)`.
+pub type EcdsaSignature = ([u8; 32], [u8; 32], RecoveryId);
+
+/// The recovery ID `v` of an Ethereum ECDSA signature: 0 or 1, or 27 or 28 as older Ethereum
+/// clients produce it. Anything else fails to decode, so can't make it into a call.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct RecoveryId(u8);
+
+impl RecoveryId {
+	/// The recovery ID `v`, if it is valid.
+	pub fn new(v: u8) -> Option<Self> {
+		match v {
+			0 | 1 | 27 | 28 => Some(RecoveryId(v)),
+			_ => None,
+		}
+	}
+
+	/// The recovery ID as 0 or 1, whichever form it was given in.
+	pub fn index(&self) -> u8 {
+		if self.0 >= 27 { self.0 - 27 } else { self.0 }
+	}
+}
+
+impl Encode for RecoveryId {
+	fn encode_to<W: Output>(&self, dest: &mut W) {
+		self.0.encode_to(dest)
+	}
+}
+
+impl Decode for RecoveryId {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		u8::decode(input).and_then(RecoveryId::new)
+	}
+}
+/// The category of an allocation, e.g. `b"team"` or `b"public"`.
+pub type Category = Vec<u8>;
+
+/// A step run on every claim before anything is written, which may reject the claim.
+///
+/// Processors compose as tuples, run in order until one of them rejects the claim.
+pub trait ClaimProcessor<AccountId, Balance> {
+	/// Check that `who` may claim `amount` for the Ethereum address `signer`.
+	fn pre_claim(who: &AccountId, signer: &EthereumAddress, amount: Balance) -> Result;
+}
+
+impl<AccountId, Balance> ClaimProcessor<AccountId, Balance> for () {
+	fn pre_claim(_: &AccountId, _: &EthereumAddress, _: Balance) -> Result {
+		Ok(())
+	}
+}
+
+macro_rules! impl_claim_processor_for_tuple {
+	( $( $p:ident ),+ ) => {
+		impl<AccountId, Balance: Copy, $( $p: ClaimProcessor<AccountId, Balance> ),+>
+			ClaimProcessor<AccountId, Balance> for ( $( $p, )+ )
+		{
+			fn pre_claim(who: &AccountId, signer: &EthereumAddress, amount: Balance) -> Result {
+				$( $p::pre_claim(who, signer, amount)?; )+
+				Ok(())
+			}
+		}
+	}
+}
+
+impl_claim_processor_for_tuple!(A);
+impl_claim_processor_for_tuple!(A, B);
+impl_claim_processor_for_tuple!(A, B, C);
+impl_claim_processor_for_tuple!(A, B, C, D);
+
+/// Rejects the claims of addresses frozen with `freeze_claim`.
+pub struct FreezeCheck<T>(PhantomData<T>);
+
+impl<T: Trait> ClaimProcessor<T::AccountId, BalanceOf<T>> for FreezeCheck<T> {
+	fn pre_claim(_: &T::AccountId, signer: &EthereumAddress, _: BalanceOf<T>) -> Result {
+		ensure!(!<Module<T>>::is_frozen(signer), "Ethereum address claim is frozen");
+		Ok(())
+	}
+}
+
+/// Rejects claims outside of `MinClaim` and `MaxClaim`.
+pub struct AmountBounds<T>(PhantomData<T>);
+
+impl<T: Trait> ClaimProcessor<T::AccountId, BalanceOf<T>> for AmountBounds<T> {
+	fn pre_claim(_: &T::AccountId, _: &EthereumAddress, amount: BalanceOf<T>) -> Result {
+		let max_claim = <Module<T>>::max_claim();
+		ensure!(amount >= <Module<T>>::min_claim(), "Claim is below the minimum");
+		ensure!(max_claim.is_zero() || amount <= max_claim, "Claim is above the maximum");
+		Ok(())
+	}
+}
+
+/// A source of the block at which each account was created.
+pub trait AccountAge<AccountId, BlockNumber> {
+	/// The block at which `who` was created, or `None` if it doesn't exist.
+	fn created_at(who: &AccountId) -> Option<BlockNumber>;
+}
+
+/// Rejects claims to accounts created less than `MinAccountAge` blocks ago, as told by `A`.
+///
+/// Neither the system nor the balances module records when an account was created, so a runtime
+/// can only use this along with a module which does.
+pub struct AccountAgeCheck<T, A>(PhantomData<(T, A)>);
+
+impl<T: Trait, A: AccountAge<T::AccountId, T::BlockNumber>> ClaimProcessor<T::AccountId, BalanceOf<T>>
+	for AccountAgeCheck<T, A>
+{
+	fn pre_claim(who: &T::AccountId, _: &EthereumAddress, _: BalanceOf<T>) -> Result {
+		let min_account_age = <Module<T>>::min_account_age();
+		if min_account_age.is_zero() {
+			return Ok(());
+		}
+
+		let created_at = A::created_at(who).ok_or("Account is too new to claim")?;
+		ensure!(
+			created_at + min_account_age <= <system::Module<T>>::block_number(),
+			"Account is too new to claim"
+		);
+		Ok(())
+	}
+}
+
+/// What is known of a claim, as found by `inspect`.
+#[derive(Clone, PartialEq, Eq, Default, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ClaimInfo<Balance> {
+	/// The address the signature recovers to, if it recovers at all.
+	pub recovered_address: Option<EthereumAddress>,
+	/// The outstanding claim of the address, if any.
+	pub amount: Option<Balance>,
+	/// Whether the claim of the address has been made.
+	pub is_claimed: bool,
+	/// Whether the claim of the address is frozen.
+	pub is_frozen: bool,
+}
+
+/// An event in this module.
+decl_event!(
+	pub enum Event<T> where
+		B = BalanceOf<T>,
+		A = <T as system::Trait>::AccountId,
+		N = <T as system::Trait>::BlockNumber
+	{
+		/// Someone claimed some DOTs: the total credited, how much of it is liquid right away,
+		/// the block it was claimed in, and whether crediting it created the account. The rest
+		/// vests, and is released with `unlock_vested`.
+		Claimed(A, EthereumAddress, B, B, N, bool),
+		/// A claim was reversed, taking back what was credited to an account and restoring the
+		/// claim of the Ethereum address.
+		ClaimReversed(A, EthereumAddress, B),
+		/// Part of a claim was withheld: the gross liquid amount, and the net amount left to
+		/// the claimant.
+		ClaimWithheld(A, EthereumAddress, B, B),
+		/// A batch of claims was made, summarised in one event rather than one `Claimed` each:
+		/// the account, address and total credited of each claim, in the order they were made.
+		BatchClaimed(Vec<(A, EthereumAddress, B)>),
+		/// A referrer was paid their share of the claim of an Ethereum address.
+		ReferrerPaid(A, EthereumAddress, B),
+		/// Vested funds of a claim were released to an account.
+		VestedReleased(A, B),
+		/// Claims were revoked: the address and amount of each, in the order they were revoked.
+		ClaimsRevoked(Vec<(EthereumAddress, B)>),
+		/// Expired claims were swept: the address and amount of each, in the order swept.
+		ClaimsExpired(Vec<(EthereumAddress, B)>),
+		/// An account failed to claim for an Ethereum address which another account claimed in
+		/// the same or the previous block, as a front-run would: the account which failed, the
+		/// address and the account which claimed.
+		ClaimFrontrunDetected(A, EthereumAddress, A),
+		/// A bonus was paid out of `BonusPool` on the claim of an Ethereum address.
+		BonusPaid(A, EthereumAddress, B),
+		/// The last outstanding claim was made, bringing `Total` to zero.
+		AllClaimsCompleted,
+		/// A claim was made, leaving the given `Total` to be claimed.
+		TotalUpdated(B),
+		/// `Total` was changed other than by a claim: from the first amount to the second.
+		TotalChanged(B, B, TotalChange),
+		/// A relayer was paid a fee out of the claim of an Ethereum address.
+		ClaimFeePaid(A, EthereumAddress, B),
+		/// An account with no balance paid the transaction fee of its claim out of the claim.
+		DeferredFeePaid(A, B),
+		/// The claim of an Ethereum address was frozen.
+		ClaimFrozen(EthereumAddress),
+		/// The claim of an Ethereum address was unfrozen.
+		ClaimUnfrozen(EthereumAddress),
+		/// `ClaimsStart` was reached, and claims may now be made.
+		ClaimsOpened,
+		/// What a claim credited an account was sent on to a location on another chain.
+		ClaimTeleported(A, EthereumAddress, MultiLocation, B),
+	}
+);
+
+/// How genesis claims are rounded to a multiple of the `rounding_unit` of the genesis config.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum Rounding {
+	/// Down to the multiple below.
+	Floor,
+	/// Up to the multiple above.
+	Ceil,
+	/// To the nearest multiple, halves rounding up.
+	Nearest,
+}
+
+impl Default for Rounding {
+	fn default() -> Self {
+		Rounding::Floor
+	}
+}
+
+impl Rounding {
+	/// `amount` rounded to a multiple of `unit`, or `amount` itself for a zero `unit`.
+	pub fn round<B: SimpleArithmetic + Copy>(self, amount: B, unit: B) -> B {
+		if unit.is_zero() {
+			return amount;
+		}
+		let rem = amount % unit;
+		let up = match self {
+			Rounding::Floor => false,
+			Rounding::Ceil => !rem.is_zero(),
+			Rounding::Nearest => rem >= unit - rem,
+		};
+		if up {
+			(amount - rem).checked_add(&unit).expect("Rounded genesis claim overflows")
+		} else {
+			amount - rem
+		}
+	}
+}
+
+/// `rate` of `amount`, worked out in `B` itself, where `Perbill`'s own `Mul` goes through
+/// `u64` and so truncates a wider balance. `B` is taken to be at least as wide as `u64`.
+pub fn perbill_of<B: SimpleArithmetic + Copy>(rate: Perbill, amount: B) -> B {
+	let billion = B::sa(1_000_000_000);
+	let parts = B::sa(rate * 1_000_000_000u64);
+	// Split so that neither product can overflow.
+	amount / billion * parts + amount % billion * parts / billion
+}
+
+/// The claims of the genesis config: those given outright, then those given as shares of
+/// `share_pool`, before rounding.
+#[cfg(feature = "std")]
+fn unrounded_genesis_claims<T: Trait>(config: &GenesisConfig<T>) -> Vec<(EthereumAddress, BalanceOf<T>)> {
+	// A share in billionths is what it makes of a billion.
+	let billionths = config.claim_shares.iter()
+		.fold(0u64, |acc, &(_, share)| acc + share * 1_000_000_000u64);
+	assert!(billionths <= 1_000_000_000, "Claim shares exceed the whole pool");
+
+	config.claims.iter().cloned()
+		.chain(config.claim_shares.iter().map(|&(a, share)| (a, perbill_of(share, config.share_pool))))
+		.collect()
+}
+
+/// The claims of the genesis config, rounded as it says.
+#[cfg(feature = "std")]
+fn genesis_claims<T: Trait>(config: &GenesisConfig<T>) -> Vec<(EthereumAddress, BalanceOf<T>)> {
+	unrounded_genesis_claims(config).into_iter()
+		.map(|(a, b)| (a, config.rounding.round(b, config.rounding_unit)))
+		.collect()
+}
+
+/// Every address the genesis config gives a claim of any kind, in the order `AddressAt` lists
+/// them.
+#[cfg(feature = "std")]
+fn genesis_addresses<T: Trait>(config: &GenesisConfig<T>) -> Vec<EthereumAddress> {
+	let mut addresses = genesis_claims(config).into_iter()
+		.filter(|&(_, b)| !b.is_zero())
+		.map(|(a, _)| a)
+		.collect::<Vec<_>>();
+	let chunked = config.chunked_claims.iter().filter(|(_, c)| !c.is_empty()).map(|(a, _)| *a);
+	let labeled = config.labeled_claims.iter().filter(|(_, l)| !l.is_empty()).map(|(a, _)| *a);
+	let foreign = config.foreign_claims.iter().filter(|(_, b)| !b.is_zero()).map(|(a, _)| *a);
+	for a in chunked.chain(labeled).chain(foreign).collect::<Vec<_>>() {
+		if !addresses.contains(&a) {
+			addresses.push(a);
+		}
+	}
+	addresses
+}
+
+/// Why `Total` was changed other than by a claim.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum TotalChange {
+	/// A claim was minted with `mint_claim`.
+	Minted,
+	/// A claim was restored with `reverse_claim`.
+	Reversed,
+	/// Claims were imported with `import_child_claims`.
+	ImportedToChildTrie,
+	/// Claims were revoked with `revoke_claims`.
+	Revoked,
+	/// Expired claims were swept with `sweep_expired`, or dropped with `kill_child_claims`.
+	Expired,
+}
+
+/// The most Ethereum signatures one extrinsic may have recovered. Each recovery is costly, so
+/// every call taking several signatures is bounded by this.
+pub const MAX_SIGNATURES_PER_EXTRINSIC: usize = 64;
+
+/// The most hashes a `claim_with_proof` proof may have, i.e. the greatest depth of the tree.
+pub const MAX_PROOF_LENGTH: usize = 32;
+
+/// The most addresses `claims_of_many` looks up at once.
+pub const MAX_ADDRESSES_PER_QUERY: usize = 256;
+
+/// The most addresses `revoke_claims` revokes at once.
+pub const MAX_REVOCATIONS_PER_CALL: usize = 256;
+
+/// The child trie holding claims imported with `import_child_claims`, each under its Ethereum
+/// address. Keeping a large distribution there keeps it out of the main trie, and lets it be
+/// dropped as a unit once it is over.
+pub const CHILD_STORAGE_KEY: &[u8] = b":child_storage:claims";
+
+/// Moves storage entries from under the prefix a module used to have, e.g. `b"Claims"`, to
+/// the one it has now, e.g. `b"EthClaims"`, after it is renamed in `decl_storage!`.
+///
+/// Storage keys are hashed, so entries can't be found by their prefix: each is moved by its
+/// unhashed key under the new prefix, as given by `key()` or `key_for()`.
+pub struct StorageMigration<'a> {
+	old: &'a [u8],
+	new: &'a [u8],
+}
+
+impl<'a> StorageMigration<'a> {
+	/// A migration from the prefix `old` to the prefix `new`.
+	pub fn new(old: &'a [u8], new: &'a [u8]) -> Self {
+		StorageMigration { old, new }
+	}
+
+	/// Move the entry with the unhashed key `key` under the new prefix from under the old one.
+	/// Returns whether there was an entry to move.
+	pub fn migrate(&self, key: &[u8]) -> bool {
+		if !key.starts_with(self.new) {
+			return false;
+		}
+		let mut old_key = self.old.to_vec();
+		old_key.extend_from_slice(&key[self.new.len()..]);
+		let old_key = sr_io::twox_128(&old_key);
+		match sr_io::storage(&old_key) {
+			Some(value) => {
+				sr_io::set_storage(&sr_io::twox_128(key), &value);
+				sr_io::clear_storage(&old_key);
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+decl_storage! {
+	// A macro for the Storage trait, and its implementation, for this module.
+	// This allows for type-safe usage of the Substrate storage database, so you can
+	// keep things around between blocks.
+	trait Store for Module<T: Trait> as Claims {
+		// Zero-valued genesis claims are dropped rather than stored: they could never credit
+		// anything and would only make `claim` succeed without effect.
+		Claims get(claims) build(|config: &GenesisConfig<T>| {
+			genesis_claims(config).into_iter()
+				.filter(|&(_, b)| !b.is_zero())
+				.collect::<Vec<_>>()
+		}): map EthereumAddress => Option<BalanceOf<T>>;
+		/// The claim each address was given at genesis. Nothing changes it, so what each address
+		/// was allocated can be audited whether or not it has claimed.
+		OriginalClaims get(original_claim) build(|config: &GenesisConfig<T>| {
+			genesis_claims(config).into_iter()
+				.filter(|&(_, b)| !b.is_zero())
+				.collect::<Vec<_>>()
+		}): map EthereumAddress => BalanceOf<T>;
+		Total get(total) build(|config: &GenesisConfig<T>| {
+			let chunks = config.chunked_claims.iter().flat_map(|(_, c)| c.iter().cloned());
+			let labeled = config.labeled_claims.iter().flat_map(|(_, l)| l.iter().map(|&(_, n)| n));
+			chunks.chain(labeled)
+				.fold(<Module<T>>::compute_total(&genesis_claims(config)), |acc: BalanceOf<T>, n| {
+					acc.checked_add(&n).expect("Total of genesis claims overflows")
+				})
+		}): BalanceOf<T>;
+		/// What rounding took off the genesis claims, and what it added to them, for the
+		/// difference to be settled with e.g. the treasury.
+		RoundingResidual get(rounding_residual) build(|config: &GenesisConfig<T>| {
+			let mut residual: (BalanceOf<T>, BalanceOf<T>) = (Zero::zero(), Zero::zero());
+			for ((_, raw), (_, rounded)) in unrounded_genesis_claims(config).into_iter().zip(genesis_claims(config)) {
+				if rounded < raw {
+					residual.0 += raw - rounded;
+				} else {
+					residual.1 += rounded - raw;
+				}
+			}
+			residual
+		}): (BalanceOf<T>, BalanceOf<T>);
+		/// The category of each claim which has one. It is kept once the claim is made if
+		/// `KeepHistory` is set, so that `reverse_claim` can restore it.
+		ClaimCategory get(claim_category) build(|config: &GenesisConfig<T>| {
+			config.categories.clone()
+		}): map EthereumAddress => Option<Category>;
+		/// The outstanding claims of each category, out of `Total`.
+		TotalByCategory get(total_by_category) build(|config: &GenesisConfig<T>| {
+			let mut totals: Vec<(Category, BalanceOf<T>)> = Vec::new();
+			let claims = genesis_claims(config);
+			for (address, category) in &config.categories {
+				let amount = claims.iter()
+					.find(|(a, _)| a == address)
+					.map_or(Zero::zero(), |&(_, b)| b);
+				match totals.iter_mut().find(|(c, _)| c == category) {
+					Some((_, total)) => *total += amount,
+					None => totals.push((category.clone(), amount)),
+				}
+			}
+			totals
+		}): map Category => BalanceOf<T>;
+		/// Allocations too large to credit at once, as the chunks to credit one after the other
+		/// with `claim_next_chunk`.
+		ChunkedClaims get(chunked_claims) build(|config: &GenesisConfig<T>| {
+			config.chunked_claims.clone()
+		}): map EthereumAddress => Vec<BalanceOf<T>>;
+		/// How many chunks of its allocation each Ethereum address has claimed so far.
+		ChunksClaimed get(chunks_claimed): map EthereumAddress => u32;
+		/// Logically separate allocations of each Ethereum address (e.g. team, advisor, bonus),
+		/// under their labels, which may be claimed one by one with `claim_label`.
+		LabeledClaims get(labeled_claims) build(|config: &GenesisConfig<T>| {
+			config.labeled_claims.clone()
+		}): map EthereumAddress => Vec<(Vec<u8>, BalanceOf<T>)>;
+		/// How many Ethereum addresses have been given a claim of any kind.
+		AddressCount get(address_count) build(|config: &GenesisConfig<T>| {
+			genesis_addresses(config).len() as u32
+		}): u32;
+		/// Every Ethereum address which has been given a claim of any kind, by the order they
+		/// were added. Storage maps can't be iterated, so this is what allows the claims to be
+		/// enumerated, a bounded number of addresses at a time.
+		AddressAt get(address_at) build(|config: &GenesisConfig<T>| {
+			genesis_addresses(config).into_iter()
+				.enumerate()
+				.map(|(i, a)| (i as u32, a))
+				.collect::<Vec<_>>()
+		}): map u32 => Option<EthereumAddress>;
+		/// The index in `AddressAt` of each Ethereum address which has been given a claim.
+		AddressIndex get(address_index) build(|config: &GenesisConfig<T>| {
+			genesis_addresses(config).into_iter()
+				.enumerate()
+				.map(|(i, a)| (a, i as u32))
+				.collect::<Vec<_>>()
+		}): map EthereumAddress => Option<u32>;
+		/// Accounts which must co-sign any claim of the given address.
+		CoSigners get(co_signer) build(|config: &GenesisConfig<T>| {
+			config.co_signers.clone()
+		}): map EthereumAddress => Option<T::AccountId>;
+		/// Accounts which may make the claim of the given address without an Ethereum
+		/// signature, for claimants who have lost their Ethereum key.
+		Recovery get(recovery) build(|config: &GenesisConfig<T>| {
+			config.recovery_accounts.clone()
+		}): map EthereumAddress => Option<T::AccountId>;
+		/// Ethereum addresses whose claim may not currently be made.
+		FrozenClaims get(is_frozen): map EthereumAddress => bool;
+		/// Which leaf indices have been claimed, for claims proven as leaves of a merkle tree
+		/// rather than stored per address. Bit `i % 8` of byte `i / 8` is set once leaf `i` is
+		/// claimed.
+		ClaimedBits get(claimed_bits): Vec<u8>;
+		/// The merkle root of the signatures recovered off-chain, for `claim_with_proof`. Each
+		/// leaf is the Keccak-256 hash of an encoded `(index, address, account)`, committing that
+		/// `address` signed the claim message for `account`.
+		RecoveryRoot get(recovery_root): Option<[u8; 32]>;
+		/// The total of all claims made so far.
+		TotalClaimed get(total_claimed): BalanceOf<T>;
+		/// Ethereum addresses whose claim has been made in full.
+		ClaimedAddresses get(is_claimed): map EthereumAddress => bool;
+		/// How many claims have been made.
+		ClaimsCount get(claims_count): u32;
+		/// Accounts which have been credited with at least one claim.
+		ClaimedAccounts get(has_claimed): map T::AccountId => bool;
+		/// How many distinct accounts have been credited with a claim; an account receiving
+		/// the claims of several addresses is only counted once.
+		TotalOfClaimedAccounts get(total_of_claimed_accounts): u32;
+		/// A phrase every claimant must sign after their account, such as a declaration the
+		/// distribution requires of them; empty for none. Claim messages are only known once
+		/// their signer is recovered, so the phrase can't differ from one address to another.
+		Statement get(statement) config(): Vec<u8>;
+		/// The prefix of the claim message, replacing `CLAIM_MESSAGE_PREFIX` unless empty. It is
+		/// read once per recovered signature, as is `Statement`.
+		Prefix get(prefix): Vec<u8>;
+		/// The distribution round, tagged onto the claim message with `round_tag` so that
+		/// signatures made for one round aren't valid in another.
+		Round get(round): u32;
+		/// How many failed claim attempts an account may make within `FailedAttemptsWindow`
+		/// blocks before it is throttled until the window is over; zero for no limit. Any claim
+		/// call whose signature doesn't recover to an address with a claim is a failed attempt
+		/// of the account which submitted it.
+		MaxFailedAttempts get(max_failed_attempts) config(): u32;
+		/// How many blocks the window of `MaxFailedAttempts` lasts.
+		FailedAttemptsWindow get(failed_attempts_window) config(): T::BlockNumber;
+		/// The failed claim attempts of each account in its current window, and the block
+		/// the window started at. Only tracked when `MaxFailedAttempts` is set.
+		FailedAttempts get(failed_attempts): map T::AccountId => (u32, T::BlockNumber);
+		/// The block from which claims may be made.
+		ClaimsStart get(claims_start) config(): T::BlockNumber;
+		/// The block from which claims may no longer be made, and what is left of them may be
+		/// swept with `sweep_expired`, or zero for never.
+		ClaimsEnd get(claims_end) config(): T::BlockNumber;
+		/// The index in `AddressAt` `sweep_expired` carries on sweeping from.
+		SweepCursor get(sweep_cursor): u32;
+		/// The index in `AddressAt` `kill_child_claims` carries on from.
+		ChildKillCursor get(child_kill_cursor): u32;
+		/// The fees `FeeFromClaim` has let accounts with no balance defer in this block, to be
+		/// taken out of what their `claim` credits. It is cleared at the end of every block.
+		DeferredFees get(deferred_fees): Vec<(T::AccountId, BalanceOf<T>)>;
+		/// How many blocks after `claim_reserve` its claim may be made with `claim_finalize`.
+		FinalizeDelay get(finalize_delay) config(): T::BlockNumber;
+		/// How many blocks old the block a `claim_block_bound` signature is bound to may be.
+		BlockBoundTolerance get(block_bound_tolerance) config(): T::BlockNumber;
+		/// The para-id of this chain, which `claim_para_bound` signatures must be bound to.
+		LocalParaId get(local_para_id) config(): u32;
+		/// The account each reserved claim is reserved for, and the block it was reserved at.
+		Reservations get(reservation): map EthereumAddress => Option<(T::AccountId, T::BlockNumber)>;
+		/// The commitment of each account to the claim it will reveal with `reveal_claim`.
+		Commitments get(commitment): map T::AccountId => Option<H256>;
+		/// The account of the claims distribution itself (e.g. the beneficiary of unclaimed
+		/// funds), which may never be the destination of a claim.
+		ModuleAccount get(module_account): Option<T::AccountId>;
+		/// The part of the liquid amount of a claim made with `claim_with_referrer` which goes
+		/// to the referrer.
+		ReferralRate get(referral_rate) config(): Perbill;
+		/// The part of the liquid amount of each claim which is withheld.
+		WithholdingRate get(withholding_rate) config(): Perbill;
+		/// The account withheld amounts are credited to.
+		WithholdingAccount get(withholding_account) config(): T::AccountId;
+		/// Whether claims are paid out of `ReserveAccount`, rather than minted.
+		PayFromReserve get(pay_from_reserve) config(): bool;
+		/// The pre-funded account claims are paid out of when `PayFromReserve` is set.
+		ReserveAccount get(reserve_account) config(): T::AccountId;
+		/// How much of the claim of each address vests rather than being liquid right away,
+		/// and the block it vests at. It is kept once the claim is made if `KeepHistory` is set,
+		/// so that `reverse_claim` can restore it.
+		Vesting get(vesting) build(|config: &GenesisConfig<T>| {
+			config.vesting.clone()
+		}): map EthereumAddress => Option<(BalanceOf<T>, T::BlockNumber)>;
+		/// The claimed funds of each account which are yet to vest, and the block each vests at.
+		Vested get(vested): map T::AccountId => Vec<(BalanceOf<T>, T::BlockNumber)>;
+		/// Whether to keep a permanent record of each claim in `History`.
+		KeepHistory get(keep_history) config(): bool;
+		/// Who claimed for each Ethereum address, how much and at which block. Only kept when
+		/// `KeepHistory` is set.
+		History get(history): map EthereumAddress => Option<(T::AccountId, BalanceOf<T>, T::BlockNumber)>;
+		/// What the claim of each Ethereum address in `History` credited the free balance of its
+		/// claimant: its liquid part, its top-up out of `TopUpPot` and its bonus out of
+		/// `BonusPool`. Kept along with `History`, so that `reverse_claim` takes back what was
+		/// paid rather than what was claimed.
+		PaidOut get(paid_out): map EthereumAddress => (BalanceOf<T>, BalanceOf<T>, BalanceOf<T>);
+		/// Whether to top up a claim which would leave the claimant below the existential
+		/// deposit, so that they end up with a usable account.
+		TopUpToED get(top_up_to_ed) config(): bool;
+		/// The funds available for topping up claims to the existential deposit. As with
+		/// `BonusPool`, they are kept out of `Total`, which only counts what is left to claim.
+		TopUpPot get(top_up_pot) config(): BalanceOf<T>;
+		/// Whether a batch credits each destination once with the sum of its claims, rather than
+		/// once per claim, so that claims each below the existential deposit aren't lost.
+		MergeBatchCredits get(merge_batch_credits) config(): bool;
+		/// The bonus paid on claims made before each deadline, as a share of the claim, by
+		/// increasing deadline. A claim gets the bonus of the first deadline it is made before.
+		BonusTiers get(bonus_tiers) config(): Vec<(T::BlockNumber, Perbill)>;
+		/// The funds bonuses are paid out of, kept apart from `Total`. Once it runs low, a bonus
+		/// is cut down to what is left.
+		BonusPool get(bonus_pool) config(): BalanceOf<T>;
+		/// Claims recorded in a foreign unit, converted at `T::ForeignRate` when they are made.
+		/// Since their value isn't known until then, they aren't counted in `Total`.
+		ForeignClaims get(foreign_claims) build(|config: &GenesisConfig<T>| {
+			config.foreign_claims.iter()
+				.filter(|&(_, b)| !b.is_zero())
+				.map(|(a, b)| (a.clone(), b.clone()))
+				.collect::<Vec<_>>()
+		}): map EthereumAddress => Option<BalanceOf<T>>;
+		/// The smallest claim `AmountBounds` lets through.
+		MinClaim get(min_claim) config(): BalanceOf<T>;
+		/// The largest claim `AmountBounds` lets through, or zero for no maximum.
+		MaxClaim get(max_claim) config(): BalanceOf<T>;
+		/// The most an account may be credited over all of its claims, or zero for no limit.
+		MaxPerAccount get(max_per_account) config(): BalanceOf<T>;
+		/// How much each account has been credited over all of its claims.
+		ClaimedBy get(claimed_by): map T::AccountId => BalanceOf<T>;
+		/// The most free balance an account may already hold for its claims to be paid in full,
+		/// or zero for no limit.
+		MaxPriorBalanceForClaim get(max_prior_balance_for_claim) config(): BalanceOf<T>;
+		/// The part of the liquid amount of a claim an account over `MaxPriorBalanceForClaim`
+		/// is still paid; the rest is forfeited. Zero rejects the claim instead.
+		RichClaimRate get(rich_claim_rate) config(): Perbill;
+		/// How many blocks old an account must be for `AccountAgeCheck` to let it claim, or
+		/// zero for no minimum. Only runtimes with a source of account ages have that check, so
+		/// this is set with `set_min_account_age` rather than at genesis.
+		MinAccountAge get(min_account_age): T::BlockNumber;
+	}
+	add_extra_genesis {
+		config(claims): Vec<(EthereumAddress, BalanceOf<T>)>;
+		config(claim_shares): Vec<(EthereumAddress, Perbill)>;
+		config(share_pool): BalanceOf<T>;
+		config(rounding): Rounding;
+		config(rounding_unit): BalanceOf<T>;
+		config(co_signers): Vec<(EthereumAddress, T::AccountId)>;
+		config(categories): Vec<(EthereumAddress, Category)>;
+		config(foreign_claims): Vec<(EthereumAddress, BalanceOf<T>)>;
+		config(chunked_claims): Vec<(EthereumAddress, Vec<BalanceOf<T>>)>;
+		config(labeled_claims): Vec<(EthereumAddress, Vec<(Vec<u8>, BalanceOf<T>)>)>;
+		config(vesting): Vec<(EthereumAddress, (BalanceOf<T>, T::BlockNumber))>;
+		config(recovery_accounts): Vec<(EthereumAddress, T::AccountId)>;
+	}
+}
+
+/// Why no public key could be recovered from an Ethereum signature.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum RecoveryError {
+	/// `r` or `s` is zero, or isn't a valid scalar.
+	InvalidSignature,
+	/// The signature is well-formed, but no public key signed the message with it.
+	NoPublicKey,
+}
+
+fn ecdsa_recover(sig: &EcdsaSignature, msg: &[u8; 32]) -> Option<[u8; 64]> {
+	try_ecdsa_recover(sig, msg).ok()
+}
+
+fn try_ecdsa_recover(sig: &EcdsaSignature, msg: &[u8; 32]) -> result::Result<[u8; 64], RecoveryError> {
+	// Neither `r` nor `s` may be zero in a valid signature; don't rely on the parser rejecting it.
+	if sig.0 == [0u8; 32] || sig.1 == [0u8; 32] {
+		return Err(RecoveryError::InvalidSignature);
+	}
+	// A `RecoveryId` can't be built out of range, so its index always parses.
+	let v = secp256k1::RecoveryId::parse(sig.2.index())
+		.map_err(|_| RecoveryError::InvalidSignature)?;
+	let rs = (sig.0, sig.1).using_encoded(secp256k1::Signature::parse_slice)
+		.map_err(|_| RecoveryError::InvalidSignature)?;
+	let pubkey = secp256k1::recover(&secp256k1::Message::parse(msg), &rs, &v)
+		.map_err(|_| RecoveryError::NoPublicKey)?;
+	let mut res = [0u8; 64];
+	res.copy_from_slice(&pubkey.serialize()[1..65]);
+	Ok(res)
+}
+
+/// The prefix of every claim message, ahead of the account being claimed for.
+///
+/// `decl_module!` can't yet expose constants in the metadata, so clients should take this from
+/// here rather than hard-coding it.
+pub const CLAIM_MESSAGE_PREFIX: &[u8] = b"Pay DOTs to the Polkadot account:";
+
+/// The byte every EIP-191 signed message starts with.
+pub const EIP191_PREFIX: u8 = 0x19;
+
+/// The EIP-191 version byte of `personal_sign` messages: the 'E' of "Ethereum".
+pub const PERSONAL_SIGN_VERSION: u8 = b'E';
+
+/// The rest of the header `personal_sign` puts ahead of the length of the message it signs.
+pub const PERSONAL_SIGN_HEADER: &[u8] = b"thereum Signed Message:\n";
+
+// Expand a signature in the 64-byte compact form of EIP-2098: `r` followed by `s`, with the
+// recovery id folded into the top bit of `s`. That bit is always clear in a normalised `s`,
+// whereas the top bit of `r` is in use, so `r` can't carry the recovery id.
+fn from_compact(sig: &H512) -> EcdsaSignature {
+	let mut r = [0u8; 32];
+	let mut s = [0u8; 32];
+	r.copy_from_slice(&sig[..32]);
+	s.copy_from_slice(&sig[32..]);
+	let v = RecoveryId(s[0] >> 7);
+	s[0] &= 0x7f;
+	(r, s, v)
+}
+
+/// How the payload (i.e. the encoded account) is laid out in a claim message.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PayloadEncoding {
+	/// The raw bytes.
+	Raw,
+	/// Lower-case hex, without a `0x` prefix.
+	Hex,
+	/// Lower-case hex, with a `0x` prefix.
+	PrefixedHex,
+}
+
+/// A framing of the claim message which some wallet is known to produce.
+struct MessageTemplate {
+	/// The prefix of the message, or `None` for the current `Prefix`, as `claim` uses.
+	prefix: Option<&'static [u8]>,
+	encoding: PayloadEncoding,
+}
+
+/// The built-in message templates, indexed by template ID:
+///
+/// 0. The standard format of `claim`: the prefix followed by the raw encoded account.
+/// 1. The account as hex, for hardware wallets which only sign printable messages.
+/// 2. The account as `0x`-prefixed hex, as some wallets display and sign it.
+/// 3. As 2, but with a space after the colon of the standard prefix, as the companion apps of
+///    some hardware wallets send it whatever `Prefix` is.
+const TEMPLATES: [MessageTemplate; 4] = [
+	MessageTemplate { prefix: None, encoding: PayloadEncoding::Raw },
+	MessageTemplate { prefix: None, encoding: PayloadEncoding::Hex },
+	MessageTemplate { prefix: None, encoding: PayloadEncoding::PrefixedHex },
+	MessageTemplate { prefix: Some(b"Pay DOTs to the Polkadot account: "), encoding: PayloadEncoding::PrefixedHex },
+];
+
+fn to_hex(data: &[u8]) -> Vec<u8> {
+	const HEX: &[u8; 16] = b"0123456789abcdef";
+	let mut res = Vec::with_capacity(data.len() * 2);
+	for byte in data {
+		res.push(HEX[(byte >> 4) as usize]);
+		res.push(HEX[(byte & 0x0f) as usize]);
+	}
+	res
+}
+
+// `data` as unpadded base64url, as the challenge is given in WebAuthn client data.
+fn to_base64url(data: &[u8]) -> Vec<u8> {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+	let mut res = Vec::with_capacity((data.len() * 4 + 2) / 3);
+	for chunk in data.chunks(3) {
+		let n = chunk.iter().enumerate().fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+		for i in 0..chunk.len() + 1 {
+			res.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize]);
+		}
+	}
+	res
+}
+
+fn contains(data: &[u8], part: &[u8]) -> bool {
+	data.windows(part.len()).any(|window| window == part)
+}
+
+fn to_decimal(mut n: u32) -> Vec<u8> {
+	let mut rev = vec![b'0' + (n % 10) as u8];
+	while n >= 10 {
+		n /= 10;
+		rev.push(b'0' + (n % 10) as u8);
+	}
+	rev.into_iter().rev().collect()
+}
+
+/// What the claim message of distribution round `round` ends with, after the statement: e.g.
+/// ` (round 2)`. Round 0 adds nothing, so messages are as they were before rounds.
+pub fn round_tag(round: u32) -> Vec<u8> {
+	if round == 0 {
+		return Vec::new();
+	}
+	let mut tag = b" (round ".to_vec();
+	tag.extend(to_decimal(round));
+	tag.push(b')');
+	tag
+}
+
+fn create_msg(who: &[u8]) -> Vec<u8> {
+	create_msg_with(&TEMPLATES[0], who, &[])
+}
+
+// The claim message for `who` as laid out by `template`, with `statement` after the account.
+fn create_msg_with(template: &MessageTemplate, who: &[u8], statement: &[u8]) -> Vec<u8> {
+	frame_msg(template.prefix.unwrap_or(CLAIM_MESSAGE_PREFIX), &template.encoding, who, statement)
+}
+
+// The claim message for `who` encoded as `encoding`, between `prefix` and `statement`.
+fn frame_msg(prefix: &[u8], encoding: &PayloadEncoding, who: &[u8], statement: &[u8]) -> Vec<u8> {
+	let mut payload = prefix.to_vec();
+	match *encoding {
+		PayloadEncoding::Raw => payload.extend_from_slice(who),
+		PayloadEncoding::Hex => payload.extend(to_hex(who)),
+		PayloadEncoding::PrefixedHex => {
+			payload.extend_from_slice(b"0x");
+			payload.extend(to_hex(who));
+		}
+	}
+	payload.extend_from_slice(statement);
+
+	// As geth, an empty message is framed with a length of "0" rather than none.
+	let mut v = vec![EIP191_PREFIX, PERSONAL_SIGN_VERSION];
+	v.extend_from_slice(PERSONAL_SIGN_HEADER);
+	v.extend(to_decimal(payload.len() as u32));
+	v.extend(payload);
+	v
+}
+
+/// The commitment `commit_claim` takes to a claim with `sig`, hidden by `salt`.
+pub fn claim_commitment(sig: &EcdsaSignature, salt: &[u8; 32]) -> H256 {
+	let mut preimage = sig.encode();
+	preimage.extend_from_slice(salt);
+	keccak256(&preimage).into()
+}
+
+// The root of the merkle tree with `leaf` at the bottom of `proof`. Each pair of hashes is
+// hashed in order, so the proof needn't say on which side each of its hashes is.
+fn merkle_root(leaf: [u8; 32], proof: &[[u8; 32]]) -> [u8; 32] {
+	proof.iter().fold(leaf, |hash, sibling| {
+		let (a, b) = if hash <= *sibling { (hash, *sibling) } else { (*sibling, hash) };
+		let mut pair = a.to_vec();
+		pair.extend_from_slice(&b);
+		keccak256(&pair)
+	})
+}
+
+// The encoding of `who` which claim messages carry. An account type encoding to nothing would
+// make every claim message the same, so that is refused.
+fn encoded_account<A: Encode>(who: &A) -> result::Result<Vec<u8>, &'static str> {
+	let data = who.encode();
+	ensure!(!data.is_empty(), "Account has an empty encoding");
+	Ok(data)
+}
+
+// Recover the address, as derived by `D`, which signed the full (i.e. already framed) message
+// `msg`.
+fn eth_recover_message<D: AddressDerivation>(s: &EcdsaSignature, msg: &[u8]) -> Option<EthereumAddress> {
+	ecdsa_recover(s, &keccak256(msg)).map(|pubkey| D::derive_address(&pubkey))
 }
 
-type EthereumAddress = [u8; 20];
-type EcdsaSignature = ([u8; 32], [u8; 32], i8);
+// Decode a base-58 string, as used by SS58 addresses.
+fn from_base58(data: &[u8]) -> Option<Vec<u8>> {
+	const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+	// The number is accumulated little-endian, then reversed.
+	let mut res = Vec::with_capacity(data.len());
+	for c in data {
+		let mut carry = ALPHABET.iter().position(|a| a == c)? as u32;
+		for byte in res.iter_mut() {
+			carry += *byte as u32 * 58;
+			*byte = carry as u8;
+			carry >>= 8;
+		}
+		while carry > 0 {
+			res.push(carry as u8);
+			carry >>= 8;
+		}
+	}
+	// Each leading '1' stands for a leading zero byte.
+	res.extend(data.iter().take_while(|&&c| c == b'1').map(|_| 0));
+	res.reverse();
+	Some(res)
+}
+
+/// The message a wallet must sign to claim for the account with the SS58 address `address`,
+/// or `None` if it isn't a valid SS58 address.
+///
+/// This is for the 32-byte accounts of the Polkadot runtime, whose encoding is their raw bytes,
+/// and for distributions without a `Statement`.
+#[cfg(feature = "std")]
+pub fn ss58_claim_message(address: &str) -> Option<Vec<u8>> {
+	let account = substrate_primitives::ed25519::Public::from_ss58check(address).ok()?;
+	Some(create_msg(&account.0))
+}
+
+/// The Ethereum address which signed the claim message of each encoded account, or `None` for
+/// an invalid signature, in the same order as `items`.
+///
+/// This reads no state, so it is for indexers checking collected signatures off-chain; as with
+/// `ss58_claim_message`, it assumes the distribution has no `Statement` and the default prefix.
+pub fn verify_many(items: &[(Vec<u8>, EcdsaSignature)]) -> Vec<Option<EthereumAddress>> {
+	items.iter()
+		.map(|(who, sig)| eth_recover_message::<()>(sig, &create_msg(who)))
+		.collect()
+}
+
+/// Parse a 65-byte signature given as hex, with or without a `0x` prefix, as wallets and RPCs
+/// display it.
+pub fn signature_from_hex(s: &str) -> result::Result<EcdsaSignature, &'static str> {
+	let s = s.as_bytes();
+	let digits = if s.starts_with(b"0x") { &s[2..] } else { s };
+	ensure!(digits.len() == 130, "Signature hex must be 65 bytes");
+
+	let nibble = |c: u8| match c {
+		b'0'..=b'9' => Ok(c - b'0'),
+		b'a'..=b'f' => Ok(c - b'a' + 10),
+		b'A'..=b'F' => Ok(c - b'A' + 10),
+		_ => Err("Invalid hex in signature"),
+	};
+	let mut bytes = Vec::with_capacity(65);
+	for pair in digits.chunks(2) {
+		bytes.push(nibble(pair[0])? << 4 | nibble(pair[1])?);
+	}
+	EcdsaSignature::decode(&mut &bytes[..]).ok_or("Invalid signature")
+}
+
+/// Render `addr` as an EIP-55 checksummed, `0x`-prefixed hex string.
+pub fn to_checksum_string(addr: &EthereumAddress) -> Vec<u8> {
+	let lower = to_hex(&addr[..]);
+	// a letter is upper case iff the corresponding nibble of the hashed lower-case hex is >= 8.
+	let hash = keccak256(&lower);
+	let mut res = b"0x".to_vec();
+	for (i, c) in lower.into_iter().enumerate() {
+		let nibble = if i % 2 == 0 { hash[i / 2] >> 4 } else { hash[i / 2] & 0x0f };
+		res.push(if nibble >= 8 { c.to_ascii_uppercase() } else { c });
+	}
+	res
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// Deposit one of this module's events by using the default implementation.
+		fn deposit_event<T>() = default;
+
+		fn on_initialise(n: T::BlockNumber) {
+			if !n.is_zero() && n == Self::claims_start() {
+				Self::deposit_event(RawEvent::ClaimsOpened);
+			}
+		}
+
+		fn on_finalise(_n: T::BlockNumber) {
+			// The fees of transactions which made no claim are forgiven.
+			<DeferredFees<T>>::kill();
+			#[cfg(any(test, feature = "try-runtime"))]
+			Self::check_total();
+		}
+
+		/// Make a claim.
+		///
+		/// As with every claim call, failed attempts are counted against the sender, who may be
+		/// throttled for a while after too many; see `MaxFailedAttempts`.
+		///
+		/// Calls have no weight to refund yet, so the checks which need nothing from the
+		/// signature are made ahead of recovering it, keeping an early failure cheap.
+		fn claim(origin, ethereum_signature: EcdsaSignature) -> Result {
+			// This is a public call, so we ensure that the origin is some signed account.
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+			Self::process_claim(signer, sender.clone(), false).map_err(|e| {
+				Self::note_frontrun(&signer, &sender);
+				e
+			})?;
+			Self::pay_deferred_fee(&sender);
+			Ok(())
+		}
+
+		/// Make a claim, paying `referrer` their share of it.
+		///
+		/// The Ethereum signature must be over the encoded `(sender, referrer)` pair, so the
+		/// referrer can't be swapped out. Without a referrer, the signature is over the sender
+		/// alone and this is a normal claim.
+		fn claim_with_referrer(origin, referrer: Option<T::AccountId>, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+			let referrer = match referrer {
+				Some(referrer) => referrer,
+				None => {
+					let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+					return Self::process_claim(signer, sender, false);
+				}
+			};
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &referrer.encode())?;
+
+			let balance_due = Self::validate_claim(&signer, &sender, false, &Pending::new())?;
+			let vesting = Self::vesting_part(&signer, balance_due);
+			let cut = Cut::Share(Self::referral_rate());
+			let payout = Self::plan_payout(&sender, balance_due, vesting, Some(cut), true, &Pending::new())?;
+			let created = Self::is_new_account(&sender);
+
+			Self::take_claim(&signer, &sender, balance_due, &payout);
+			let (total, credited) = Self::pay_out(&signer, &sender, &payout, Some(&referrer))?;
+
+			Self::deposit_event(RawEvent::ReferrerPaid(referrer, signer, payout.cut));
+			Self::deposit_event(RawEvent::Claimed(
+				sender, signer, total, credited, <system::Module<T>>::block_number(), created
+			));
+		}
+
+		/// Make a claim, checking that the signature recovers to the `expected` address.
+		fn claim_checked(origin, expected: EthereumAddress, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+			ensure!(signer == expected, "Signature recovers to another Ethereum address");
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make a claim with a signature which is only valid up to the block `valid_until`, so a
+		/// pre-signed claim can't be submitted indefinitely.
+		///
+		/// The Ethereum signature must be over the encoded `(sender, valid_until)` pair.
+		fn claim_with_expiry(origin, ethereum_signature: EcdsaSignature, valid_until: T::BlockNumber) {
+			let sender = ensure_signed(origin)?;
+			ensure!(
+				<system::Module<T>>::block_number() <= valid_until,
+				"Claim signature has expired"
+			);
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &valid_until.encode())?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make a claim with a signature bound to the recent block `at`, which may be at most
+		/// `BlockBoundTolerance` blocks old, so a pre-signed claim is only valid for a while.
+		///
+		/// The Ethereum signature must be over the encoded `(sender, at)` pair.
+		fn claim_block_bound(origin, ethereum_signature: EcdsaSignature, at: T::BlockNumber) {
+			let sender = ensure_signed(origin)?;
+			let now = <system::Module<T>>::block_number();
+			ensure!(at <= now, "Claim is bound to a future block");
+			ensure!(now - at <= Self::block_bound_tolerance(), "Claim is bound to a block too long ago");
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &at.encode())?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make a claim with a signature bound to the parachain `para_id`, which must be this
+		/// chain's `LocalParaId`, so a signature meant for one parachain can't be used on another.
+		///
+		/// The Ethereum signature must be over the encoded `(sender, para_id)` pair.
+		fn claim_para_bound(origin, ethereum_signature: EcdsaSignature, para_id: u32) {
+			let sender = ensure_signed(origin)?;
+			ensure!(para_id == Self::local_para_id(), "Claim is bound to another parachain");
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &para_id.encode())?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make a claim where the Ethereum signature is over the claim message of the sender's
+		/// account hashed with `T::PreHashing`, rather than of the account itself.
+		fn claim_prehashed(origin, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_throttled(&sender, || {
+				let data = encoded_account(&sender)?;
+				Self::eth_recover(&ethereum_signature, &T::PreHashing::pre_hash(&data))
+					.ok_or("Invalid Ethereum signature")
+			})?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make a claim with a signature in the compact form of EIP-2098, as some wallets
+		/// produce, rather than as `(r, s, v)`.
+		fn claim_compact(origin, ethereum_signature: H512) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &from_compact(&ethereum_signature), &sender, &[])?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make the claim of `ethereum_address`, whose signature for the sender was recovered
+		/// off-chain, with a merkle proof that leaf `index` of `RecoveryRoot` commits to it. This
+		/// hashes a little rather than recovering a signature. Each leaf may be used once.
+		fn claim_with_proof(origin, index: u32, ethereum_address: EthereumAddress, proof: Vec<[u8; 32]>) {
+			let sender = ensure_signed(origin)?;
+			ensure!(proof.len() <= MAX_PROOF_LENGTH, "Recovery proof is too long");
+			let root = Self::recovery_root().ok_or("No recovery root is set")?;
+
+			let leaf = keccak256(&(index, ethereum_address, &sender).encode());
+			ensure!(merkle_root(leaf, &proof) == root, "Invalid recovery proof");
+			ensure!(!Self::is_index_claimed(index), "Leaf index already claimed");
+
+			Self::process_claim(ethereum_address, sender, false)?;
+			Self::set_index_claimed(index)?;
+		}
+
+		/// Claim the next chunk of a chunked allocation.
+		fn claim_next_chunk(origin, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+
+			let chunks = Self::chunked_claims(&signer);
+			let index = Self::chunks_claimed(&signer);
+			let chunk = *chunks.get(index as usize).ok_or("Ethereum address has no chunk left to claim")?;
+			Self::check_claim(&signer, &sender, false, chunk, &Pending::new())?;
+			let payout = Self::plan_payout(&sender, chunk, Zero::zero(), None, true, &Pending::new())?;
+
+			if index == 0 {
+				Self::note_claim(&sender);
+			}
+
+			if index as usize + 1 == chunks.len() {
+				<ChunkedClaims<T>>::remove(&signer);
+				<ClaimedAddresses<T>>::insert(&signer, true);
+				<ChunksClaimed<T>>::remove(&signer);
+			} else {
+				<ChunksClaimed<T>>::insert(&signer, index + 1);
+			}
+			Self::note_claimed_by(&sender, chunk);
+			Self::reduce_total(chunk);
+			let created = Self::is_new_account(&sender);
+			let (total, credited) = Self::pay_out(&signer, &sender, &payout, None)?;
+
+			Self::deposit_event(RawEvent::Claimed(
+				sender, signer, total, credited, <system::Module<T>>::block_number(), created
+			));
+		}
+
+		/// Make a claim where the Ethereum signature is over the keccak-256 hash of the sender's
+		/// SS58 address, as some tools produce.
+		///
+		/// Unlike every other claim message, this one skips the EIP-191 framing, so the signed
+		/// message is just the address string, followed by the statement and round as in
+		/// `claim_message`. Its checksum isn't verified: it only guards against typos, and the
+		/// account it carries is checked against the sender.
+		fn claim_ss58_digest(origin, ss58_address: Vec<u8>, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_throttled(&sender, || {
+				Self::recover_ss58_digest(&sender, &ss58_address, &ethereum_signature)
+			})?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make a claim, registering `name` as the display name of the sender with
+		/// `T::Identity`, so a new account starts out with an identity.
+		///
+		/// The Ethereum signature must be over the encoded `(sender, name)` pair, so the name
+		/// can't be swapped out.
+		fn claim_with_identity(origin, ethereum_signature: EcdsaSignature, name: Vec<u8>) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &name.encode())?;
+			T::Identity::can_set_display_name(&sender, &name)?;
+
+			Self::process_claim(signer, sender.clone(), false)?;
+			T::Identity::set_display_name(&sender, name);
+		}
+
+		/// Make a claim and send what it credits to `dest` on another chain through
+		/// `T::Teleport`, as an XCM teleport. What vests stays with the sender until released.
+		///
+		/// The Ethereum signature must be over the encoded `(sender, dest)` pair, so the
+		/// destination can't be swapped out.
+		fn claim_to_location(origin, ethereum_signature: EcdsaSignature, dest: MultiLocation) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &dest.encode())?;
+			T::Teleport::can_teleport(&dest)?;
+
+			let created = Self::is_new_account(&sender);
+			let (total, liquid) = Self::apply_claim(signer, &sender, false)?;
+			// Just credited, so this can't fail.
+			T::Currency::withdraw(&sender, liquid)?;
+			T::Teleport::teleport(&dest, liquid);
+
+			Self::deposit_event(RawEvent::Claimed(
+				sender.clone(), signer, total, liquid, <system::Module<T>>::block_number(), created
+			));
+			Self::deposit_event(RawEvent::ClaimTeleported(sender, signer, dest, liquid));
+		}
+
+		/// Make a claim with a WebAuthn assertion of a passkey, rather than an Ethereum
+		/// signature. The claim is that of the address `T::AddressDerivation` derives from the
+		/// P-256 public key of the passkey, which `T::WebAuthn` verifies the assertion with.
+		///
+		/// The challenge of the assertion must be the Keccak-256 hash of the claim message of the
+		/// sender's account, and its client data must be as the browser gave it.
+		fn claim_webauthn(
+			origin,
+			public_key: H512,
+			authenticator_data: Vec<u8>,
+			client_data_json: Vec<u8>,
+			signature: H512
+		) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_throttled(&sender, || {
+				Self::recover_webauthn(&sender, &public_key, &authenticator_data, &client_data_json, &signature)
+			})?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Claim the allocation under `label` of a labeled allocation.
+		fn claim_label(origin, ethereum_signature: EcdsaSignature, label: Vec<u8>) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+
+			let mut labeled = Self::labeled_claims(&signer);
+			let index = labeled.iter().position(|(l, _)| l == &label)
+				.ok_or("Ethereum address has no claim under the label")?;
+			let amount = labeled[index].1;
+			Self::check_claim(&signer, &sender, false, amount, &Pending::new())?;
+
+			labeled.remove(index);
+			Self::take_labeled(&signer, &sender, labeled, amount)?;
+		}
+
+		/// Claim every remaining allocation of a labeled allocation at once, crediting their
+		/// sum.
+		fn claim_all_labels(origin, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+
+			let labeled = Self::labeled_claims(&signer);
+			ensure!(!labeled.is_empty(), "Ethereum address has no labeled claim");
+			let amount = labeled.iter().fold(Zero::zero(), |acc: BalanceOf<T>, &(_, b)| acc + b);
+			Self::check_claim(&signer, &sender, false, amount, &Pending::new())?;
+
+			Self::take_labeled(&signer, &sender, vec![], amount)?;
+		}
+
+		/// Make a claim of an address which has a co-signer bound to it.
+		///
+		/// The co-signer must have signed the encoded `(sender, ethereum_address)` pair.
+		fn claim_cosigned(origin, ethereum_signature: EcdsaSignature, co_signature: T::CoSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+
+			let co_signer = Self::co_signer(&signer).ok_or("Ethereum address has no co-signer")?;
+			ensure!(
+				co_signature.verify(&(&sender, &signer).encode()[..], &co_signer),
+				"Invalid co-signature"
+			);
+
+			Self::process_claim(signer, sender, true)?;
+		}
+
+		/// Make a claim on behalf of `dest`, paying the sender `fee` out of it.
+		///
+		/// The Ethereum signature must be over the encoded `(dest, fee)` pair, so the relayer
+		/// can neither redirect the claim nor raise its fee.
+		fn claim_with_fee(origin, dest: T::AccountId, ethereum_signature: EcdsaSignature, fee: BalanceOf<T>) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &dest, &fee.encode())?;
+
+			let balance_due = Self::validate_claim(&signer, &dest, false, &Pending::new())?;
+			let vesting = Self::vesting_part(&signer, balance_due);
+			// The fee can only come out of the liquid part of the claim.
+			let payout = Self::plan_payout(&dest, balance_due, vesting, Some(Cut::Fee(fee)), true, &Pending::new())?;
+			let created = Self::is_new_account(&dest);
+
+			Self::take_claim(&signer, &dest, balance_due, &payout);
+			let (total, credited) = Self::pay_out(&signer, &dest, &payout, Some(&sender))?;
+
+			Self::deposit_event(RawEvent::ClaimFeePaid(sender, signer, fee));
+			Self::deposit_event(RawEvent::Claimed(
+				dest, signer, total, credited, <system::Module<T>>::block_number(), created
+			));
+		}
+
+		/// Make a claim on behalf of the account `source` resolves to.
+		///
+		/// The Ethereum signature is over the resolved account, just as in `claim`; this lets a
+		/// claimant who only knows their account by its index or another address form have
+		/// someone else submit the claim for them.
+		fn claim_via_lookup(origin, source: <T::Lookup as StaticLookup>::Source, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+			let dest = T::Lookup::lookup(source)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &dest, &[])?;
+
+			Self::process_claim(signer, dest, false)?;
+		}
+
+		/// Make a claim to `dest`, with the Ethereum signature over `dest` just as in `claim`.
+		///
+		/// This is how a claim is made to an account which can't easily submit a call itself,
+		/// such as a multisig: the claim message is signed over the multisig account, and any
+		/// account, e.g. one of its signatories, submits the claim. The funds can only land in
+		/// `dest`, so nothing needs to go through the multisig's approval.
+		fn claim_to(origin, dest: T::AccountId, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &dest, &[])?;
+
+			Self::process_claim(signer, dest, false)?;
+		}
+
+		/// Make a claim where the Ethereum signature is over one of the built-in message
+		/// templates rather than the standard message.
+		fn claim_with_template(origin, ethereum_signature: EcdsaSignature, template_id: u8) {
+			let sender = ensure_signed(origin)?;
+			let template = TEMPLATES.get(template_id as usize).ok_or("Unknown message template")?;
+
+			let signer = Self::recover_throttled(&sender, || {
+				let data = encoded_account(&sender)?;
+				eth_recover_message::<T::AddressDerivation>(
+					&ethereum_signature,
+					&Self::template_message(template, &data),
+				).ok_or("Invalid Ethereum signature")
+			})?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make a claim where the Ethereum signature is over any of the built-in message templates,
+		/// trying each in turn until one recovers an address with a claim.
+		///
+		/// This may recover one signature for every template, so it costs up to that many times
+		/// what `claim_with_template` does, which should be preferred when the template is known.
+		fn claim_auto(origin, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_throttled(&sender, || {
+				let data = encoded_account(&sender)?;
+				TEMPLATES.iter()
+					.filter_map(|template| eth_recover_message::<T::AddressDerivation>(
+						&ethereum_signature,
+						&Self::template_message(template, &data),
+					))
+					.find(Self::has_claim)
+					.ok_or("Ethereum address has no claim")
+			})?;
+
+			Self::process_claim(signer, sender, false)?;
+		}
+
+		/// Make the claim of `ethereum_address` from its recovery account, without an Ethereum
+		/// signature.
+		fn claim_signed_by_substrate(origin, ethereum_address: EthereumAddress) {
+			let sender = ensure_signed(origin)?;
+			ensure!(
+				Self::recovery(&ethereum_address).as_ref() == Some(&sender),
+				"Sender is not the recovery account of the Ethereum address"
+			);
+
+			Self::process_claim(ethereum_address, sender, false)?;
+		}
+
+		/// Apply a batch of claims as part of a one-off migration.
+		///
+		/// Each entry is a destination account with an Ethereum signature over it, as in `claim`.
+		/// Every entry is checked before any is applied, so the batch applies in full or not at
+		/// all.
+		fn migrate_claims(origin, claims: Vec<(T::AccountId, EcdsaSignature)>) -> Result {
+			ensure_inherent(origin)?;
+			Self::process_batch(None, claims, false)
+		}
+
+		/// Make a batch of claims, each for a destination account with an Ethereum signature
+		/// over it, as in `claim`.
+		///
+		/// The batch applies in full or not at all, and the claims are made, and their events
+		/// deposited, in the order given. With `aggregate`, a single `BatchClaimed` event is
+		/// deposited in place of the `Claimed` event of each claim.
+		fn claim_batch(origin, claims: Vec<(T::AccountId, EcdsaSignature)>, aggregate: bool) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::process_batch(Some(&sender), claims, aggregate)
+		}
+
+		/// Reserve the claim of the address which signed the claim message for the sender's
+		/// account, so that it can only be made to the sender. It is made with `claim_finalize`
+		/// once `FinalizeDelay` blocks have passed.
+		fn claim_reserve(origin, ethereum_signature: EcdsaSignature) {
+			let sender = ensure_signed(origin)?;
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+			ensure!(Self::reservation(&signer).is_none(), "Ethereum address claim is already reserved");
+			Self::validate_claim(&signer, &sender, false, &Pending::new())?;
+
+			<Reservations<T>>::insert(&signer, (sender, <system::Module<T>>::block_number()));
+		}
+
+		/// Make the claim of `ethereum_address` which the sender reserved.
+		fn claim_finalize(origin, ethereum_address: EthereumAddress) {
+			let sender = ensure_signed(origin)?;
+			let reserved_at = Self::reserved_by(&ethereum_address, &sender)?;
+			ensure!(
+				<system::Module<T>>::block_number() >= reserved_at + Self::finalize_delay(),
+				"Reserved claim may not be finalized yet"
+			);
+
+			Self::process_claim(ethereum_address, sender, false)?;
+			<Reservations<T>>::remove(&ethereum_address);
+		}
+
+		/// Cancel the reservation of the claim of `ethereum_address` which the sender made.
+		fn cancel_reserve(origin, ethereum_address: EthereumAddress) {
+			let sender = ensure_signed(origin)?;
+			Self::reserved_by(&ethereum_address, &sender)?;
+			<Reservations<T>>::remove(&ethereum_address);
+		}
+
+		/// Commit to a claim without yet revealing its signature, so that it can't be taken from
+		/// the transaction queue and front-run. `commitment` is the Keccak-256 hash of the encoded
+		/// signature followed by a salt, and replaces any earlier commitment of the sender.
+		fn commit_claim(origin, commitment: H256) {
+			let sender = ensure_signed(origin)?;
+			<Commitments<T>>::insert(&sender, commitment);
+		}
+
+		/// Make the claim the sender committed to with `commit_claim`.
+		fn reveal_claim(origin, ethereum_signature: EcdsaSignature, salt: [u8; 32]) {
+			let sender = ensure_signed(origin)?;
+			let commitment = Self::commitment(&sender).ok_or("No claim has been committed to")?;
+			ensure!(
+				claim_commitment(&ethereum_signature, &salt) == commitment,
+				"Claim doesn't match its commitment"
+			);
+
+			let signer = Self::recover_claimant(&sender, &ethereum_signature, &sender, &[])?;
+
+			Self::process_claim(signer, sender.clone(), false)?;
+			<Commitments<T>>::remove(&sender);
+		}
+
+		/// Claim to the sender the claims of several Ethereum addresses, each signature being over
+		/// the sender's account as in `claim`.
+		///
+		/// Signatures recovering an address already seen are skipped, so a duplicated signature is
+		/// claimed once. The others apply in full or not at all, and a single `BatchClaimed` event
+		/// lists the distinct claims made.
+		///
+		/// This is how the addresses an HD wallet derives from one seed are claimed together: the
+		/// runtime knows nothing of the derivation, so the wallet signs the sender's account with
+		/// the key of each address it holds a claim for. At most `MAX_SIGNATURES_PER_EXTRINSIC`
+		/// addresses are claimed at once; a wallet with more makes several calls.
+		fn claim_multiple(origin, ethereum_signatures: Vec<EcdsaSignature>) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::ensure_signature_count(ethereum_signatures.len())?;
+
+			let mut claims: Vec<(T::AccountId, EthereumAddress)> = Vec::with_capacity(ethereum_signatures.len());
+			for ethereum_signature in &ethereum_signatures {
+				let signer = Self::recover_claimant(&sender, ethereum_signature, &sender, &[])?;
+				if !claims.iter().any(|(_, s)| s == &signer) {
+					claims.push((sender.clone(), signer));
+				}
+			}
+			Self::make_batch(claims, false, true)
+		}
+
+		/// Release the claimed funds of the sender which have vested.
+		fn unlock_vested(origin) {
+			let sender = ensure_signed(origin)?;
+			let now = <system::Module<T>>::block_number();
+
+			let (vested, pending): (Vec<_>, Vec<_>) = Self::vested(&sender).into_iter()
+				.partition(|&(_, at)| at <= now);
+			let released = vested.into_iter().fold(Zero::zero(), |acc: BalanceOf<T>, (b, _)| acc + b);
+			ensure!(!released.is_zero(), "No vested funds to release");
+
+			Self::credit(&sender, released)?;
+			if pending.is_empty() {
+				<Vested<T>>::remove(&sender);
+			} else {
+				<Vested<T>>::insert(&sender, pending);
+			}
+
+			Self::deposit_event(RawEvent::VestedReleased(sender, released));
+		}
+
+		/// Bind a co-signer to an Ethereum address, or remove the binding with `None`.
+		pub fn set_co_signer(who: EthereumAddress, co_signer: Option<T::AccountId>) {
+			match co_signer {
+				Some(co_signer) => <CoSigners<T>>::insert(&who, co_signer),
+				None => <CoSigners<T>>::remove(&who),
+			}
+		}
+
+		/// Bind a recovery account to an Ethereum address, or remove the binding with `None`.
+		pub fn set_recovery(who: EthereumAddress, recovery: Option<T::AccountId>) {
+			match recovery {
+				Some(recovery) => <Recovery<T>>::insert(&who, recovery),
+				None => <Recovery<T>>::remove(&who),
+			}
+		}
+
+		/// Add `value` to the claim of `who`, giving it a claim if it has none.
+		pub fn mint_claim(who: EthereumAddress, value: BalanceOf<T>) -> Result {
+			let total = Self::total().checked_add(&value).ok_or("Total of claims would overflow")?;
+			let claim = match Self::claims(&who) {
+				Some(claim) => claim.checked_add(&value).ok_or("Claim would overflow")?,
+				None => value,
+			};
+
+			Self::note_address(&who);
+			<Claims<T>>::insert(&who, claim);
+			Self::set_total(total, TotalChange::Minted);
+			Ok(())
+		}
+
+		/// Reverse a claim which was made to the wrong account: take what it paid back from
+		/// `from` and restore `amount` as the claim of `who`. The claim must be in `History` as
+		/// made to `from` for `amount`, so only claims made while `KeepHistory` was set can be
+		/// reversed. What of it is still vesting is taken back out of `Vested`, and what it
+		/// credited, as kept in `PaidOut`, out of the free balance of `from`: it is returned to
+		/// `ReserveAccount` with `PayFromReserve` set, and its top-up and bonus to their pots.
+		///
+		/// What was withheld or cut from the claim was paid to other accounts, and isn't taken
+		/// back.
+		pub fn reverse_claim(who: EthereumAddress, from: T::AccountId, amount: BalanceOf<T>) -> Result {
+			ensure!(Self::claims(&who).is_none(), "Ethereum address still has a claim");
+			ensure!(Self::is_claimed(&who), "Ethereum address hasn't been claimed");
+			ensure!(
+				Self::history(&who).map_or(false, |(dest, claimed, _)| dest == from && claimed == amount),
+				"Ethereum address wasn't claimed by that account for that amount"
+			);
+			let total = Self::total().checked_add(&amount).ok_or("Total of claims would overflow")?;
+			let vesting = Self::vesting_part(&who, amount);
+			let mut vested = Self::vested(&from);
+			let locked = Self::vesting(&who).and_then(|(_, at)| vested.iter().position(|&v| v == (vesting, at)));
+			let (liquid, top_up, bonus) = Self::paid_out(&who);
+			// Vested funds which have been released were credited as the rest was.
+			let released = if locked.is_some() { Zero::zero() } else { vesting };
+			let paid = liquid + top_up + bonus + released;
+
+			T::Currency::withdraw(&from, paid)?;
+			if Self::pay_from_reserve() {
+				T::Currency::deposit_creating(&Self::reserve_account(), paid);
+			}
+			<TopUpPot<T>>::mutate(|p| *p += top_up);
+			<BonusPool<T>>::mutate(|p| *p += bonus);
+			if let Some(index) = locked {
+				vested.remove(index);
+				if vested.is_empty() {
+					<Vested<T>>::remove(&from);
+				} else {
+					<Vested<T>>::insert(&from, vested);
+				}
+			}
+			Self::note_address(&who);
+			<Claims<T>>::insert(&who, amount);
+			if let Some(category) = Self::claim_category(&who) {
+				<TotalByCategory<T>>::mutate(&category, |t| *t += amount);
+			}
+			Self::set_total(total, TotalChange::Reversed);
+			<TotalClaimed<T>>::mutate(|t| *t -= amount);
+			<History<T>>::remove(&who);
+			<PaidOut<T>>::remove(&who);
+			<ClaimedAddresses<T>>::remove(&who);
+			Self::unnote_claim(&from, amount);
+
+			Self::deposit_event(RawEvent::ClaimReversed(from, who, amount));
+			Ok(())
+		}
+
+		/// Revoke the claims of `addrs`, e.g. as their keys are known to be compromised, so they
+		/// can never be made. Every kind of claim is revoked, as it is swept once claims expire,
+		/// along with any reservation of it. Addresses without a claim are skipped, and foreign
+		/// claims, which `Total` doesn't cover, are listed as revoking nothing from it. At most
+		/// `MAX_REVOCATIONS_PER_CALL` may be given.
+		pub fn revoke_claims(addrs: Vec<EthereumAddress>) -> Result {
+			ensure!(addrs.len() <= MAX_REVOCATIONS_PER_CALL, "Too many addresses to revoke");
+
+			let mut revoked = Vec::new();
+			let mut total = Self::total();
+			for who in addrs {
+				let foreign = <ForeignClaims<T>>::take(&who).is_some();
+				<Reservations<T>>::remove(&who);
+				match Self::take_outstanding(&who) {
+					Some(amount) => {
+						total -= amount;
+						revoked.push((who, amount));
+					}
+					None if foreign => revoked.push((who, Zero::zero())),
+					None => {}
+				}
+			}
+
+			Self::set_total(total, TotalChange::Revoked);
+			Self::deposit_event(RawEvent::ClaimsRevoked(revoked));
+			Ok(())
+		}
+
+		/// Sweep the claims of up to `limit` more addresses of `AddressAt` once claims have
+		/// closed at `ClaimsEnd`, taking what is left of them out of `Total`. Sweeping carries
+		/// on from where the last call stopped, so a large distribution is swept over several
+		/// blocks by calling this until `SweepCursor` reaches `AddressCount`.
+		///
+		/// Every kind of claim counted in `Total` is swept, so once `SweepCursor` reaches the end
+		/// `Total` is zero, whatever is left of `TopUpPot`.
+		pub fn sweep_expired(limit: u32) -> Result {
+			Self::ensure_claims_expired()?;
+
+			let from = Self::sweep_cursor();
+			let to = Self::address_count().min(from.saturating_add(limit));
+			let mut swept = Vec::new();
+			let mut total = Self::total();
+			for index in from..to {
+				let who = match Self::address_at(index) {
+					Some(who) => who,
+					None => continue,
+				};
+				if let Some(amount) = Self::take_outstanding(&who) {
+					total -= amount;
+					swept.push((who, amount));
+				}
+			}
+
+			<SweepCursor<T>>::put(to.max(from));
+			Self::set_total(total, TotalChange::Expired);
+			Self::deposit_event(RawEvent::ClaimsExpired(swept));
+			Ok(())
+		}
+
+		/// Add claims to the child trie, as `mint_claim` does to the main trie.
+		pub fn import_child_claims(claims: Vec<(EthereumAddress, BalanceOf<T>)>) -> Result {
+			let mut total = Self::total();
+			for (who, value) in &claims {
+				ensure!(
+					Self::claims(who).is_none() && Self::child_claim(who).is_none(),
+					"Ethereum address already has a claim"
+				);
+				total = total.checked_add(value).ok_or("Total of claims would overflow")?;
+			}
+
+			for (who, value) in claims {
+				Self::note_address(&who);
+				sr_io::set_child_storage(CHILD_STORAGE_KEY, &who, &value.encode());
+			}
+			Self::set_total(total, TotalChange::ImportedToChildTrie);
+			Ok(())
+		}
+
+		/// Drop the claims imported with `import_child_claims` of up to `limit` more addresses
+		/// of `AddressAt` once claims have closed at `ClaimsEnd`, taking what is left of them out
+		/// of `Total`. As with `sweep_expired`, this carries on from where the last call stopped,
+		/// and once `ChildKillCursor` reaches `AddressCount` the child trie is dropped as a unit.
+		///
+		/// Only the child trie is read and written, so this is lighter than sweeping the same
+		/// addresses.
+		pub fn kill_child_claims(limit: u32) -> Result {
+			Self::ensure_claims_expired()?;
+
+			let from = Self::child_kill_cursor();
+			let count = Self::address_count();
+			let to = count.min(from.saturating_add(limit));
+			let mut swept = Vec::new();
+			let mut total = Self::total();
+			for index in from..to {
+				let who = match Self::address_at(index) {
+					Some(who) => who,
+					None => continue,
+				};
+				if let Some(amount) = Self::child_claim(&who) {
+					sr_io::clear_child_storage(CHILD_STORAGE_KEY, &who);
+					total -= amount;
+					swept.push((who, amount));
+				}
+			}
+
+			if to >= count {
+				sr_io::kill_child_storage(CHILD_STORAGE_KEY);
+				<ChildKillCursor<T>>::kill();
+			} else {
+				<ChildKillCursor<T>>::put(to.max(from));
+			}
+			Self::set_total(total, TotalChange::Expired);
+			Self::deposit_event(RawEvent::ClaimsExpired(swept));
+			Ok(())
+		}
+
+		/// Clear the storage of the claims of up to `limit` addresses of `AddressAt`, once there is
+		/// nothing left to claim and claims have closed at `ClaimsEnd`. Once `AddressCount` is
+		/// zero, the totals are cleared too, so this is called until it is.
+		///
+		/// Claimed funds still vesting, and what is kept per account, are left in place, as
+		/// accounts can't be enumerated.
+		pub fn kill_claims_storage(limit: u32) -> Result {
+			ensure!(Self::total().is_zero(), "Claims are still outstanding");
+			Self::ensure_claims_expired()?;
+
+			let count = Self::address_count();
+			let keep = count.saturating_sub(limit);
+			for index in keep..count {
+				let who = match <AddressAt<T>>::take(index) {
+					Some(who) => who,
+					None => continue,
+				};
+				<AddressIndex<T>>::remove(&who);
+				<Claims<T>>::remove(&who);
+				if let Some(category) = <ClaimCategory<T>>::take(&who) {
+					<TotalByCategory<T>>::remove(&category);
+				}
+				<ChunkedClaims<T>>::remove(&who);
+				<ChunksClaimed<T>>::remove(&who);
+				<LabeledClaims<T>>::remove(&who);
+				<CoSigners<T>>::remove(&who);
+				<Recovery<T>>::remove(&who);
+				<FrozenClaims<T>>::remove(&who);
+				<ClaimedAddresses<T>>::remove(&who);
+				<Reservations<T>>::remove(&who);
+				<Vesting<T>>::remove(&who);
+				<History<T>>::remove(&who);
+				<PaidOut<T>>::remove(&who);
+				<ForeignClaims<T>>::remove(&who);
+			}
+
+			if keep == 0 {
+				<AddressCount<T>>::kill();
+				<Total<T>>::kill();
+				<TotalClaimed<T>>::kill();
+				<ClaimedBits<T>>::kill();
+				<ClaimsCount<T>>::kill();
+				<TotalOfClaimedAccounts<T>>::kill();
+				<SweepCursor<T>>::kill();
+				<ChildKillCursor<T>>::kill();
+				<RoundingResidual<T>>::kill();
+			} else {
+				<AddressCount<T>>::put(keep);
+			}
+			Ok(())
+		}
+
+		/// Move the storage of the module from under the prefix `old`, after the module is
+		/// renamed, so what was stored before is found again. Called once, in the block of the
+		/// runtime upgrade.
+		///
+		/// Every value is moved, as are the entries under each address of `AddressAt`. Accounts
+		/// can't be enumerated, so what is kept per account is only moved for `accounts`.
+		pub fn migrate_storage_prefix(old: Vec<u8>, accounts: Vec<T::AccountId>) -> Result {
+			let key = <Total<T>>::key();
+			let new = &key[..key.len() - b" Total".len()];
+			ensure!(&old[..] != new, "Storage is already under this prefix");
+			let migration = StorageMigration::new(&old, new);
+
+			for key in &[
+				<Total<T>>::key(), <RoundingResidual<T>>::key(), <AddressCount<T>>::key(),
+				<ClaimedBits<T>>::key(), <RecoveryRoot<T>>::key(), <TotalClaimed<T>>::key(),
+				<ClaimsCount<T>>::key(), <TotalOfClaimedAccounts<T>>::key(), <Statement<T>>::key(),
+				<Prefix<T>>::key(), <Round<T>>::key(), <MaxFailedAttempts<T>>::key(), <FailedAttemptsWindow<T>>::key(),
+				<ClaimsStart<T>>::key(), <ClaimsEnd<T>>::key(), <SweepCursor<T>>::key(),
+				<ChildKillCursor<T>>::key(), <FinalizeDelay<T>>::key(), <BlockBoundTolerance<T>>::key(),
+				<LocalParaId<T>>::key(), <ModuleAccount<T>>::key(), <ReferralRate<T>>::key(),
+				<WithholdingRate<T>>::key(), <WithholdingAccount<T>>::key(), <PayFromReserve<T>>::key(),
+				<ReserveAccount<T>>::key(), <KeepHistory<T>>::key(),
+				<TopUpToED<T>>::key(), <TopUpPot<T>>::key(), <MergeBatchCredits<T>>::key(),
+				<BonusTiers<T>>::key(), <BonusPool<T>>::key(), <MinClaim<T>>::key(),
+				<MaxClaim<T>>::key(), <MaxPerAccount<T>>::key(), <MaxPriorBalanceForClaim<T>>::key(),
+				<RichClaimRate<T>>::key(), <MinAccountAge<T>>::key(),
+			] {
+				migration.migrate(key);
+			}
+
+			let mut categories = Vec::new();
+			for index in 0..Self::address_count() {
+				migration.migrate(&<AddressAt<T>>::key_for(&index));
+				let who = match Self::address_at(index) {
+					Some(who) => who,
+					None => continue,
+				};
+				for key in &[
+					<Claims<T>>::key_for(&who), <OriginalClaims<T>>::key_for(&who),
+					<AddressIndex<T>>::key_for(&who), <ClaimCategory<T>>::key_for(&who),
+					<ChunkedClaims<T>>::key_for(&who), <ChunksClaimed<T>>::key_for(&who),
+					<LabeledClaims<T>>::key_for(&who), <CoSigners<T>>::key_for(&who),
+					<Recovery<T>>::key_for(&who), <FrozenClaims<T>>::key_for(&who),
+					<ClaimedAddresses<T>>::key_for(&who), <Reservations<T>>::key_for(&who),
+					<Vesting<T>>::key_for(&who), <History<T>>::key_for(&who),
+					<PaidOut<T>>::key_for(&who), <ForeignClaims<T>>::key_for(&who),
+				] {
+					migration.migrate(key);
+				}
+				if let Some(category) = Self::claim_category(&who) {
+					if !categories.contains(&category) {
+						categories.push(category);
+					}
+				}
+			}
+			for category in categories {
+				migration.migrate(&<TotalByCategory<T>>::key_for(&category));
+			}
+
+			for who in accounts {
+				for key in &[
+					<ClaimedAccounts<T>>::key_for(&who), <FailedAttempts<T>>::key_for(&who),
+					<Commitments<T>>::key_for(&who), <Vested<T>>::key_for(&who),
+					<ClaimedBy<T>>::key_for(&who),
+				] {
+					migration.migrate(key);
+				}
+			}
+			Ok(())
+		}
+	}
+}
+
+// A part of the liquid amount of a claim paid to another account than the claimant.
+enum Cut<Balance> {
+	// A fixed amount, e.g. a relayer's fee.
+	Fee(Balance),
+	// A share, e.g. a referrer's.
+	Share(Perbill),
+}
+
+// How a claim is paid out, as planned by `plan_payout` before anything is written.
+struct Payout<T: Trait> {
+	// The part which vests, and is only credited once it has.
+	vesting: BalanceOf<T>,
+	// What the claimant is credited, and what that is topped up by.
+	liquid: BalanceOf<T>,
+	top_up: BalanceOf<T>,
+	// What is withheld, the bonus, and the cut paid to another account.
+	withheld: BalanceOf<T>,
+	bonus: BalanceOf<T>,
+	cut: BalanceOf<T>,
+}
+
+impl<T: Trait> Payout<T> {
+	// What is credited now, and so drawn from the reserve when paying out of it.
+	fn drawn(&self) -> BalanceOf<T> {
+		self.liquid + self.top_up + self.withheld + self.bonus + self.cut
+	}
+}
+
+// What the claims of a batch checked so far will pay out, so that each later claim is checked
+// as if they had been made.
+struct Pending<T: Trait> {
+	// What each destination claims, and what it is credited.
+	claimed: Vec<(T::AccountId, BalanceOf<T>)>,
+	credited: Vec<(T::AccountId, BalanceOf<T>)>,
+	// What is drawn from the reserve, `BonusPool` and `TopUpPot`.
+	drawn: BalanceOf<T>,
+	bonus: BalanceOf<T>,
+	top_up: BalanceOf<T>,
+}
+
+impl<T: Trait> Pending<T> {
+	fn new() -> Self {
+		Pending {
+			claimed: Vec::new(),
+			credited: Vec::new(),
+			drawn: Zero::zero(),
+			bonus: Zero::zero(),
+			top_up: Zero::zero(),
+		}
+	}
+
+	// What `who` claims.
+	fn claimed(&self, who: &T::AccountId) -> BalanceOf<T> {
+		Self::get(&self.claimed, who)
+	}
+
+	// What `who` is credited.
+	fn credited(&self, who: &T::AccountId) -> BalanceOf<T> {
+		Self::get(&self.credited, who)
+	}
+
+	// Count the claim of `balance_due` by `dest`, paid out as `payout`.
+	fn add(&mut self, dest: &T::AccountId, balance_due: BalanceOf<T>, payout: &Payout<T>) {
+		Self::increase(&mut self.claimed, dest, balance_due);
+		Self::increase(&mut self.credited, dest, payout.liquid + payout.top_up + payout.bonus);
+		self.drawn += payout.drawn();
+		self.bonus += payout.bonus;
+		self.top_up += payout.top_up;
+	}
+
+	// Count a top-up of `dest` made apart from the payout of its claims.
+	fn add_top_up(&mut self, dest: &T::AccountId, top_up: BalanceOf<T>) {
+		Self::increase(&mut self.credited, dest, top_up);
+		self.drawn += top_up;
+		self.top_up += top_up;
+	}
+
+	fn get(amounts: &[(T::AccountId, BalanceOf<T>)], who: &T::AccountId) -> BalanceOf<T> {
+		amounts.iter().find(|(a, _)| a == who).map_or(Zero::zero(), |&(_, b)| b)
+	}
+
+	fn increase(amounts: &mut Vec<(T::AccountId, BalanceOf<T>)>, who: &T::AccountId, value: BalanceOf<T>) {
+		if !amounts.iter().any(|(a, _)| a == who) {
+			amounts.push((who.clone(), Zero::zero()));
+		}
+		if let Some((_, b)) = amounts.iter_mut().find(|(a, _)| a == who) {
+			*b += value;
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The `Total` of `claims` as the genesis build computes it, so that tools generating a
+	/// chain spec can check theirs against it. The genesis build rounds the claims first, and
+	/// adds the chunked and labeled claims, so those are left to the tool.
+	///
+	/// Panics if the total overflows, as the genesis build does.
+	pub fn compute_total(claims: &[(EthereumAddress, BalanceOf<T>)]) -> BalanceOf<T> {
+		claims.iter().fold(Zero::zero(), |acc: BalanceOf<T>, &(_, n)| {
+			acc.checked_add(&n).expect("Total of genesis claims overflows")
+		})
+	}
+
+	/// Every address which has been given a claim of any kind, in the order they were added.
+	///
+	/// This reads the whole of `AddressAt`, so as with `export_claims` it is meant for use
+	/// off-chain rather than from within the runtime.
+	pub fn addresses() -> Vec<EthereumAddress> {
+		(0..Self::address_count()).filter_map(|index| Self::address_at(index)).collect()
+	}
+
+	// Add `who` to the addresses which have been given a claim, unless it already is one.
+	fn note_address(who: &EthereumAddress) {
+		if Self::address_index(who).is_none() {
+			let index = Self::address_count();
+			<AddressAt<T>>::insert(index, *who);
+			<AddressIndex<T>>::insert(who, index);
+			<AddressCount<T>>::put(index + 1);
+		}
+	}
+
+	/// All outstanding claims, in the order they were added.
+	///
+	/// This reads every address ever given a claim, so it is meant for snapshots and audits
+	/// off-chain rather than for use from within the runtime.
+	pub fn export_claims() -> Vec<(EthereumAddress, BalanceOf<T>)> {
+		Self::addresses().into_iter()
+			.filter_map(|a| Self::claims(&a).map(|b| (a, b)))
+			.collect()
+	}
+
+	/// The total of the outstanding claims, and how many claims have been made, for the
+	/// metrics of nodes.
+	pub fn metrics() -> (BalanceOf<T>, u32) {
+		(Self::total(), Self::claims_count())
+	}
+
+	/// The claim of each of `addrs`, in the same order, for dashboards checking many addresses
+	/// at once. At most `MAX_ADDRESSES_PER_QUERY` may be given.
+	pub fn claims_of_many(
+		addrs: &[EthereumAddress]
+	) -> result::Result<Vec<Option<BalanceOf<T>>>, &'static str> {
+		ensure!(addrs.len() <= MAX_ADDRESSES_PER_QUERY, "Too many addresses in one query");
+		Ok(addrs.iter().map(Self::claims).collect())
+	}
+
+	/// The address `sig` recovers to as a signature of the claim message for the encoded account
+	/// `who`, or why it recovers to none. Claims only fail with "Invalid Ethereum signature", so
+	/// this is for telling the causes apart.
+	pub fn diagnose_signature(who: &[u8], sig: &EcdsaSignature) -> result::Result<EthereumAddress, RecoveryError> {
+		try_ecdsa_recover(sig, &Self::recovery_digest(who))
+			.map(|pubkey| T::AddressDerivation::derive_address(&pubkey))
+	}
+
+	/// What is known of the claim of the address `sig` recovers to as a signature of the claim
+	/// message for the encoded account `who`.
+	pub fn inspect(who: &[u8], sig: &EcdsaSignature) -> ClaimInfo<BalanceOf<T>> {
+		match Self::eth_recover(sig, who) {
+			Some(signer) => ClaimInfo {
+				recovered_address: Some(signer),
+				amount: Self::claims(&signer),
+				is_claimed: Self::is_claimed(&signer),
+				is_frozen: Self::is_frozen(&signer),
+			},
+			None => ClaimInfo::default(),
+		}
+	}
+
+	/// The fee a claimant will pay for a claim transaction `encoded_len` bytes long, or zero if
+	/// it is submitted unsigned.
+	pub fn estimate_fee(encoded_len: usize, unsigned: bool) -> BalanceOf<T> {
+		if unsigned {
+			Zero::zero()
+		} else {
+			T::TransactionFee::fee(encoded_len)
+		}
+	}
+
+	/// The message the claimant must sign to claim for the encoded account `who`, including the
+	/// current `Prefix`, `Statement` and `Round`.
+	pub fn claim_message(who: &[u8]) -> Vec<u8> {
+		Self::template_message(&TEMPLATES[0], who)
+	}
+
+	/// The message signed for `claim_ss58_digest` by the owner of `ss58_address`: the address
+	/// string as is, then the statement and the round tag.
+	pub fn ss58_digest_message(ss58_address: &[u8]) -> Vec<u8> {
+		let mut msg = ss58_address.to_vec();
+		msg.extend(Self::statement());
+		msg.extend(round_tag(Self::round()));
+		msg
+	}
+
+	/// The exact bytes a claim signature for the encoded account `who` is recovered over, i.e.
+	/// the framed `claim_message`, for diagnosing a signature recovering to the wrong address.
+	pub fn recovery_preimage(who: &[u8]) -> Vec<u8> {
+		Self::claim_message(who)
+	}
+
+	/// The Keccak-256 hash of `recovery_preimage`, which is what is actually signed.
+	pub fn recovery_digest(who: &[u8]) -> [u8; 32] {
+		keccak256(&Self::recovery_preimage(who))
+	}
+
+	/// The prefix every claim message currently starts with.
+	pub fn message_prefix() -> Vec<u8> {
+		let prefix = Self::prefix();
+		if prefix.is_empty() { CLAIM_MESSAGE_PREFIX.to_vec() } else { prefix }
+	}
+
+	// The claim message for `who` as laid out by `template`, with its own prefix or else the
+	// current one, and the round tagged on after the statement.
+	fn template_message(template: &MessageTemplate, who: &[u8]) -> Vec<u8> {
+		let mut statement = Self::statement();
+		statement.extend(round_tag(Self::round()));
+		let prefix = template.prefix.map_or_else(Self::message_prefix, |prefix| prefix.to_vec());
+		frame_msg(&prefix, &template.encoding, who, &statement)
+	}
+
+	// Recover the address which signed the claim message for the account `who`, followed by
+	// `extra`, e.g. the encoded fee or deadline the signature also covers. Fails for an account
+	// which encodes to nothing.
+	fn recover_signer<A: Encode>(s: &EcdsaSignature, who: &A, extra: &[u8])
+		-> result::Result<EthereumAddress, &'static str>
+	{
+		let mut data = encoded_account(who)?;
+		data.extend_from_slice(extra);
+		Self::eth_recover(s, &data).ok_or("Invalid Ethereum signature")
+	}
+
+	// As `recover_signer`, for a claim call made by `sender`.
+	fn recover_claimant<A: Encode>(sender: &T::AccountId, s: &EcdsaSignature, who: &A, extra: &[u8])
+		-> result::Result<EthereumAddress, &'static str>
+	{
+		Self::recover_throttled(sender, || Self::recover_signer(s, who, extra))
+	}
+
+	// Recover a signer with `recover` for a claim call made by `sender`. Fails at once if claims
+	// aren't open or `sender` is throttled, and counts a failed attempt against it if no address
+	// with a claim is recovered, as the call can then only fail. That is kept even though the
+	// call fails, as nothing is rolled back.
+	fn recover_throttled<F>(sender: &T::AccountId, recover: F) -> result::Result<EthereumAddress, &'static str>
+		where F: FnOnce() -> result::Result<EthereumAddress, &'static str>
+	{
+		Self::ensure_claims_open()?;
+		Self::ensure_not_throttled(sender)?;
+		let signer = recover();
+		if signer.as_ref().map_or(true, |signer| !Self::has_claim(signer)) {
+			Self::note_failed_attempt(sender);
+		}
+		signer
+	}
+
+	// Recover the address which signed the `claim_ss58_digest` message of `ss58_address`, which
+	// must be the address of `sender`.
+	fn recover_ss58_digest(sender: &T::AccountId, ss58_address: &[u8], s: &EcdsaSignature)
+		-> result::Result<EthereumAddress, &'static str>
+	{
+		// A version byte, the encoded account and two checksum bytes.
+		let decoded = from_base58(ss58_address).ok_or("Invalid SS58 address")?;
+		ensure!(decoded.len() > 3, "Invalid SS58 address");
+		ensure!(
+			encoded_account(sender)? == &decoded[1..decoded.len() - 2],
+			"SS58 address is not that of the sender"
+		);
+		eth_recover_message::<T::AddressDerivation>(s, &Self::ss58_digest_message(ss58_address))
+			.ok_or("Invalid Ethereum signature")
+	}
+
+	// The address of the passkey `public_key` which signed the `claim_webauthn` assertion of
+	// `authenticator_data` and `client_data_json` for `sender`.
+	fn recover_webauthn(
+		sender: &T::AccountId,
+		public_key: &H512,
+		authenticator_data: &[u8],
+		client_data_json: &[u8],
+		signature: &H512
+	) -> result::Result<EthereumAddress, &'static str> {
+		let mut challenge = b"\"challenge\":\"".to_vec();
+		challenge.extend(to_base64url(&keccak256(&Self::claim_message(&encoded_account(sender)?))));
+		challenge.push(b'"');
+		ensure!(
+			contains(client_data_json, b"\"type\":\"webauthn.get\""),
+			"WebAuthn client data is not of an assertion"
+		);
+		ensure!(contains(client_data_json, &challenge), "WebAuthn challenge is not the claim message");
+		ensure!(
+			T::WebAuthn::verify_assertion(&public_key.0, authenticator_data, client_data_json, &signature.0),
+			"Invalid WebAuthn assertion"
+		);
+		Ok(T::AddressDerivation::derive_address(&public_key.0))
+	}
+
+	// Recover the address which signed the claim message for the encoded account `who`.
+	fn eth_recover(s: &EcdsaSignature, who: &[u8]) -> Option<EthereumAddress> {
+		eth_recover_message::<T::AddressDerivation>(s, &Self::claim_message(who))
+	}
+
+	/// Whether the leaf at `index` has been claimed.
+	pub fn is_index_claimed(index: u32) -> bool {
+		let (byte, bit) = ((index / 8) as usize, index % 8);
+		Self::claimed_bits().get(byte).map_or(false, |b| b & (1 << bit) != 0)
+	}
+
+	// Mark the leaf at `index` as claimed. Fails if it already is.
+	fn set_index_claimed(index: u32) -> Result {
+		ensure!(!Self::is_index_claimed(index), "Leaf index already claimed");
+
+		let (byte, bit) = ((index / 8) as usize, index % 8);
+		<ClaimedBits<T>>::mutate(|bits| {
+			if bits.len() <= byte {
+				bits.resize(byte + 1, 0);
+			}
+			bits[byte] |= 1 << bit;
+		});
+		Ok(())
+	}
+
+	// Credit `dest` with the claim of `signer`. Fails if `signer` has nothing to claim.
+	fn process_claim(signer: EthereumAddress, dest: T::AccountId, co_signed: bool) -> Result {
+		let created = Self::is_new_account(&dest);
+		let (total, liquid) = Self::apply_claim(signer, &dest, co_signed)?;
+
+		// Let's deposit an event to let the outside world know this happened.
+		Self::deposit_event(RawEvent::Claimed(
+			dest, signer, total, liquid, <system::Module<T>>::block_number(), created
+		));
+
+		Ok(())
+	}
+
+	// Take the fee `sender` deferred with `FeeFromClaim`, if any, out of what its claim just
+	// credited, as far as its free balance covers it.
+	fn pay_deferred_fee(sender: &T::AccountId) {
+		let mut fees = Self::deferred_fees();
+		if let Some(i) = fees.iter().position(|(who, _)| who == sender) {
+			let (_, fee) = fees.swap_remove(i);
+			let fee = rstd::cmp::min(fee, T::Currency::free_balance(sender));
+			// No more than the free balance, so this can't fail.
+			let _ = T::Currency::withdraw(sender, fee);
+			<DeferredFees<T>>::put(fees);
+			Self::deposit_event(RawEvent::DeferredFeePaid(sender.clone(), fee));
+		}
+	}
+
+	// As `process_claim`, but returning the total credited and its liquid part rather than
+	// depositing `Claimed`.
+	fn apply_claim(signer: EthereumAddress, dest: &T::AccountId, co_signed: bool)
+		-> result::Result<(BalanceOf<T>, BalanceOf<T>), &'static str>
+	{
+		let balance_due = Self::validate_claim(&signer, dest, co_signed, &Pending::new())?;
+		let vesting = Self::vesting_part(&signer, balance_due);
+		let payout = Self::plan_payout(dest, balance_due, vesting, None, true, &Pending::new())?;
+
+		Self::take_claim(&signer, dest, balance_due, &payout);
+		Self::pay_out(&signer, dest, &payout, None)
+	}
+
+	// Plan the payout of a claim of `balance_due` to `dest`, `vesting` of which vests, as if the
+	// claims of `pending` had been made. Every kind of claim is paid out this way, so that each
+	// is withheld from, earns its bonus and is topped up alike: a rich `dest` forfeits all but
+	// `RichClaimRate` of the liquid part, `WithholdingRate` of what is left is withheld, and
+	// `cut` of the rest is paid to another account. The liquid part is topped up to the
+	// existential deposit if `top_up` is set.
+	//
+	// Nothing is written, so this is called before any claim path touches storage. It fails if
+	// the reserve can't cover what is paid out.
+	fn plan_payout(
+		dest: &T::AccountId,
+		balance_due: BalanceOf<T>,
+		vesting: BalanceOf<T>,
+		cut: Option<Cut<BalanceOf<T>>>,
+		top_up: bool,
+		pending: &Pending<T>
+	) -> result::Result<Payout<T>, &'static str> {
+		let paid = Self::rich_part(dest, balance_due - vesting, pending);
+		let withheld = perbill_of(Self::withholding_rate(), paid);
+		let liquid = paid - withheld;
+		let cut = match cut {
+			Some(Cut::Fee(fee)) => {
+				ensure!(fee <= liquid, "Fee exceeds the claim");
+				fee
+			}
+			Some(Cut::Share(rate)) => perbill_of(rate, liquid),
+			None => Zero::zero(),
+		};
+		let liquid = liquid - cut;
+		let bonus = Self::bonus_due(balance_due, pending);
+		let top_up = if top_up { Self::top_up_due(dest, liquid + bonus, pending) } else { Zero::zero() };
+
+		let payout = Payout { vesting, liquid, top_up, withheld, bonus, cut };
+		Self::ensure_reserve_covers(pending.drawn + payout.drawn())?;
+		Ok(payout)
+	}
+
+	// Pay out `payout`, as planned for the claim of `signer` taken for `dest`, with its cut going
+	// to `cut_to`. Returns the total credited to `dest` and its liquid part.
+	fn pay_out(signer: &EthereumAddress, dest: &T::AccountId, payout: &Payout<T>, cut_to: Option<&T::AccountId>)
+		-> result::Result<(BalanceOf<T>, BalanceOf<T>), &'static str>
+	{
+		Self::pay_deductions(signer, dest, payout, cut_to)?;
+		let credited = Self::pay_liquid(dest, payout.liquid, payout.top_up)?;
+		Ok((credited + payout.vesting, credited))
+	}
+
+	// Pay out what `payout` pays apart from the liquid part of the claim of `signer`: what is
+	// withheld, the bonus and the cut.
+	fn pay_deductions(signer: &EthereumAddress, dest: &T::AccountId, payout: &Payout<T>, cut_to: Option<&T::AccountId>)
+		-> Result
+	{
+		if !payout.withheld.is_zero() {
+			Self::credit(&Self::withholding_account(), payout.withheld)?;
+			let liquid = payout.liquid + payout.cut;
+			Self::deposit_event(RawEvent::ClaimWithheld(dest.clone(), *signer, liquid + payout.withheld, liquid));
+		}
+
+		if !payout.bonus.is_zero() {
+			Self::credit(dest, payout.bonus)?;
+			<BonusPool<T>>::mutate(|p| *p -= payout.bonus);
+			Self::deposit_event(RawEvent::BonusPaid(dest.clone(), *signer, payout.bonus));
+		}
+
+		if let Some(who) = cut_to {
+			Self::credit(who, payout.cut)?;
+		}
+		Ok(())
+	}
+
+	// Credit `dest` with `liquid`, and with `top_up` out of `TopUpPot`. Returns what is
+	// credited.
+	fn pay_liquid(dest: &T::AccountId, liquid: BalanceOf<T>, top_up: BalanceOf<T>)
+		-> result::Result<BalanceOf<T>, &'static str>
+	{
+		Self::credit(dest, liquid + top_up)?;
+		if !top_up.is_zero() {
+			<TopUpPot<T>>::mutate(|p| *p -= top_up);
+		}
+		Ok(liquid + top_up)
+	}
+
+	// Make each of `claims` in order, having checked all of them first. A batch submitted by
+	// `sender`, rather than as an inherent, counts its failed attempts against it.
+	fn process_batch(sender: Option<&T::AccountId>, claims: Vec<(T::AccountId, EcdsaSignature)>, aggregate: bool)
+		-> Result
+	{
+		Self::ensure_claims_open()?;
+		Self::ensure_signature_count(claims.len())?;
+
+		let mut batch: Vec<(T::AccountId, EthereumAddress)> = Vec::with_capacity(claims.len());
+		for (dest, ethereum_signature) in claims {
+			let signer = match sender {
+				Some(sender) => Self::recover_claimant(sender, &ethereum_signature, &dest, &[])?,
+				None => Self::recover_signer(&ethereum_signature, &dest, &[])?,
+			};
+			ensure!(!batch.iter().any(|(_, s)| s == &signer), "Ethereum address claimed twice in batch");
+			batch.push((dest, signer));
+		}
+		Self::make_batch(batch, Self::merge_batch_credits(), aggregate)
+	}
+
+	// Make the `claims` of a batch, each of an Ethereum address for an account, in order. Each
+	// is checked as if the earlier ones had been made, and all of them before any is made, so
+	// the batch applies in full or not at all.
+	//
+	// With `merge`, each destination is credited once with the sum of the liquid parts of its
+	// claims, so that claims each below the existential deposit can together be above it. With
+	// `aggregate`, a single `BatchClaimed` event is deposited in place of the `Claimed` event of
+	// each claim.
+	fn make_batch(claims: Vec<(T::AccountId, EthereumAddress)>, merge: bool, aggregate: bool) -> Result {
+		let mut pending = Pending::new();
+		let mut planned = Vec::with_capacity(claims.len());
+		for (dest, signer) in claims {
+			let balance_due = Self::validate_claim(&signer, &dest, false, &pending)?;
+			let vesting = Self::vesting_part(&signer, balance_due);
+			let payout = Self::plan_payout(&dest, balance_due, vesting, None, !merge, &pending)?;
+			pending.add(&dest, balance_due, &payout);
+			planned.push((dest, signer, balance_due, payout));
+		}
+
+		// The credit of each destination of a merged batch: the liquid parts of its claims, the
+		// top-up of their sum, and whether it creates the account.
+		let mut credits: Vec<(T::AccountId, BalanceOf<T>, BalanceOf<T>, bool)> = Vec::new();
+		if merge {
+			for (dest, _, _, payout) in &planned {
+				if !credits.iter().any(|(d, _, _, _)| d == dest) {
+					credits.push((dest.clone(), Zero::zero(), Zero::zero(), Self::is_new_account(dest)));
+				}
+				if let Some((_, liquid, _, _)) = credits.iter_mut().find(|(d, _, _, _)| d == dest) {
+					*liquid += payout.liquid;
+				}
+			}
+			for (dest, _, top_up, _) in credits.iter_mut() {
+				// What the batch credits `dest` is already pending.
+				*top_up = Self::top_up_due(dest, Zero::zero(), &pending);
+				pending.add_top_up(dest, *top_up);
+				Self::ensure_reserve_covers(pending.drawn)?;
+			}
+		}
+
+		let now = <system::Module<T>>::block_number();
+		let mut made = Vec::with_capacity(planned.len());
+		for (dest, signer, balance_due, payout) in planned {
+			let created = Self::is_new_account(&dest);
+			Self::take_claim(&signer, &dest, balance_due, &payout);
+			let (total, liquid) = if merge {
+				Self::pay_deductions(&signer, &dest, &payout, None)?;
+				(payout.vesting + payout.liquid, payout.liquid)
+			} else {
+				Self::pay_out(&signer, &dest, &payout, None)?
+			};
+			if !merge && !aggregate {
+				// Let's deposit an event to let the outside world know this happened.
+				Self::deposit_event(RawEvent::Claimed(dest.clone(), signer, total, liquid, now, created));
+			}
+			made.push((dest, signer, total, liquid));
+		}
+		for (dest, liquid, top_up, _) in &credits {
+			Self::pay_liquid(dest, *liquid, *top_up)?;
+		}
+
+		if aggregate {
+			let summary = made.into_iter().map(|(dest, signer, total, _)| (dest, signer, total)).collect();
+			Self::deposit_event(RawEvent::BatchClaimed(summary));
+		} else if merge {
+			for (dest, signer, total, liquid) in made {
+				// Every claim of a destination shares its one credit, so each is marked as
+				// creating the account if the credit did.
+				let created = credits.iter().any(|(d, _, _, created)| d == &dest && *created);
+				Self::deposit_event(RawEvent::Claimed(dest, signer, total, liquid, now, created));
+			}
+		}
+		Ok(())
+	}
+
+	// Check that `who` hasn't failed too many claim attempts in its current window.
+	fn ensure_not_throttled(who: &T::AccountId) -> Result {
+		let max_failed_attempts = Self::max_failed_attempts();
+		if max_failed_attempts == 0 {
+			return Ok(());
+		}
+
+		let (attempts, start) = Self::failed_attempts(who);
+		let now = <system::Module<T>>::block_number();
+		ensure!(
+			attempts < max_failed_attempts || now >= start + Self::failed_attempts_window(),
+			"Too many failed claim attempts"
+		);
+		Ok(())
+	}
+
+	// Count a failed claim attempt of `who`, starting a new window if the last one is over.
+	fn note_failed_attempt(who: &T::AccountId) {
+		if Self::max_failed_attempts() == 0 {
+			return;
+		}
+
+		let now = <system::Module<T>>::block_number();
+		<FailedAttempts<T>>::mutate(who, |(attempts, start)| {
+			if now >= *start + Self::failed_attempts_window() {
+				*attempts = 1;
+				*start = now;
+			} else {
+				*attempts += 1;
+			}
+		});
+	}
+
+	// Report `who` failing to claim for `signer` if another account just claimed for it. This
+	// relies on `History`, so goes unreported unless `KeepHistory` is set.
+	fn note_frontrun(signer: &EthereumAddress, who: &T::AccountId) {
+		if let Some((claimant, _, at)) = Self::history(signer) {
+			let now = <system::Module<T>>::block_number();
+			if &claimant != who && now <= at + One::one() {
+				Self::deposit_event(RawEvent::ClaimFrontrunDetected(who.clone(), *signer, claimant));
+			}
+		}
+	}
+
+	// Check that claims have closed at `ClaimsEnd`, so what is left of them may be cleared away.
+	fn ensure_claims_expired() -> Result {
+		let end = Self::claims_end();
+		ensure!(
+			!end.is_zero() && <system::Module<T>>::block_number() >= end,
+			"Claims have not yet expired"
+		);
+		Ok(())
+	}
+
+	// Take what is left of every kind of claim of `who` counted in `Total`, returning it, or
+	// `None` if `who` has none left.
+	fn take_outstanding(who: &EthereumAddress) -> Option<BalanceOf<T>> {
+		let mut left = None;
+		if let Some(amount) = <Claims<T>>::take(who) {
+			if let Some(category) = <ClaimCategory<T>>::take(who) {
+				Self::reduce_category_total(&category, amount);
+			}
+			<Vesting<T>>::remove(who);
+			left = Some(amount);
+		} else if let Some(amount) = Self::child_claim(who) {
+			sr_io::clear_child_storage(CHILD_STORAGE_KEY, who);
+			left = Some(amount);
+		}
+
+		let claimed = <ChunksClaimed<T>>::take(who) as usize;
+		let chunks = <ChunkedClaims<T>>::take(who).into_iter().skip(claimed);
+		let labeled = <LabeledClaims<T>>::take(who).into_iter().map(|(_, amount)| amount);
+		for amount in chunks.chain(labeled) {
+			left = Some(left.unwrap_or_else(Zero::zero) + amount);
+		}
+		left
+	}
+
+	// Check that `count` signatures may be recovered in one extrinsic.
+	fn ensure_signature_count(count: usize) -> Result {
+		ensure!(count <= MAX_SIGNATURES_PER_EXTRINSIC, "Too many signatures in one extrinsic");
+		Ok(())
+	}
+
+	// Check that the claim of `signer` may be made by `dest`, as if the claims of `pending` had
+	// been made, returning the amount due. Nothing is written, so every check must pass before
+	// any claim path touches storage.
+	fn validate_claim(signer: &EthereumAddress, dest: &T::AccountId, co_signed: bool, pending: &Pending<T>)
+		-> result::Result<BalanceOf<T>, &'static str>
+	{
+		let balance_due = match Self::claims(signer).or_else(|| Self::child_claim(signer)) {
+			Some(balance_due) => balance_due,
+			None => Self::foreign_claim_value(signer)?,
+		};
+		Self::check_claim(signer, dest, co_signed, balance_due, pending)?;
+
+		Ok(balance_due)
+	}
+
+	// Panic if `Total` isn't the sum of every claim of `AddressAt` it counts:
+	// those in `Claims` and the child trie, the chunks left of chunked claims and the labeled
+	// claims, so that accounting drift fails tests at once. Foreign claims aren't valued until
+	// they are made, so as in `Total` they are left out.
+	#[cfg(any(test, feature = "try-runtime"))]
+	fn check_total() {
+		let sum = Self::addresses().into_iter().fold(Zero::zero(), |sum: BalanceOf<T>, who| {
+			let claim = Self::claims(&who).or_else(|| Self::child_claim(&who)).unwrap_or_else(Zero::zero);
+			let chunks = Self::chunked_claims(&who).into_iter()
+				.skip(Self::chunks_claimed(&who) as usize)
+				.fold(Zero::zero(), |sum: BalanceOf<T>, chunk| sum + chunk);
+			let labeled = Self::labeled_claims(&who).into_iter()
+				.fold(Zero::zero(), |sum: BalanceOf<T>, (_, amount)| sum + amount);
+			sum + claim + chunks + labeled
+		});
+		assert!(Self::total() == sum, "Total has drifted from the sum of claims");
+	}
+
+	// Whether `signer` has a claim of any kind.
+	fn has_claim(signer: &EthereumAddress) -> bool {
+		Self::claims(signer).is_some()
+			|| Self::child_claim(signer).is_some()
+			|| Self::foreign_claims(signer).is_some()
+			|| !Self::chunked_claims(signer).is_empty()
+			|| !Self::labeled_claims(signer).is_empty()
+	}
+
+	// Check that `dest` may claim `amount` for `signer`, whatever the kind of claim, as if the
+	// claims of `pending` had been made.
+	fn check_claim(
+		signer: &EthereumAddress,
+		dest: &T::AccountId,
+		co_signed: bool,
+		amount: BalanceOf<T>,
+		pending: &Pending<T>
+	) -> Result {
+		Self::ensure_claims_open()?;
+		ensure!(
+			Self::module_account().as_ref() != Some(dest),
+			"Claims may not be made to the module account"
+		);
+		ensure!(
+			co_signed || Self::co_signer(signer).is_none(),
+			"Ethereum address requires a co-signature"
+		);
+		ensure!(
+			Self::reservation(signer).map_or(true, |(account, _)| &account == dest),
+			"Ethereum address claim is reserved for another account"
+		);
+		let max_per_account = Self::max_per_account();
+		ensure!(
+			max_per_account.is_zero() || Self::claimed_by(dest)
+				.checked_add(&pending.claimed(dest))
+				.and_then(|claimed| claimed.checked_add(&amount))
+				.map_or(false, |claimed| claimed <= max_per_account),
+			"Claim would take the account over its limit"
+		);
+		ensure!(
+			!Self::is_rich(dest, pending) || Self::rich_claim_rate() != Perbill::zero(),
+			"Account already holds too much to claim"
+		);
+		T::Processor::pre_claim(dest, signer, amount)
+	}
+
+	// Check that claims may be made in this block.
+	fn ensure_claims_open() -> Result {
+		let now = <system::Module<T>>::block_number();
+		ensure!(now >= Self::claims_start(), "Claims are not yet open");
+		let end = Self::claims_end();
+		ensure!(end.is_zero() || now < end, "Claims have closed");
+		Ok(())
+	}
+
+	// The block at which `who` reserved the claim of `signer`.
+	fn reserved_by(signer: &EthereumAddress, who: &T::AccountId) -> result::Result<T::BlockNumber, &'static str> {
+		let (account, reserved_at) = Self::reservation(signer).ok_or("Ethereum address claim is not reserved")?;
+		ensure!(&account == who, "Ethereum address claim is reserved for another account");
+		Ok(reserved_at)
+	}
+
+	// The value of the foreign claim of `signer` at the current rate.
+	fn foreign_claim_value(signer: &EthereumAddress) -> result::Result<BalanceOf<T>, &'static str> {
+		let amount = Self::foreign_claims(signer).ok_or("Ethereum address has no claim")?;
+		let rate = T::ForeignRate::rate().ok_or("No rate to convert the foreign claim")?;
+		amount.checked_mul(&rate).ok_or("Foreign claim overflows when converted")
+	}
+
+	/// The claim of `who` in the child trie, if any.
+	pub fn child_claim(who: &EthereumAddress) -> Option<BalanceOf<T>> {
+		sr_io::child_storage(CHILD_STORAGE_KEY, who)
+			.and_then(|v| Decode::decode(&mut &v[..]))
+	}
+
+	// How much of a claim of `balance_due` by `signer` vests rather than being liquid.
+	fn vesting_part(signer: &EthereumAddress, balance_due: BalanceOf<T>) -> BalanceOf<T> {
+		match Self::vesting(signer) {
+			Some((vesting, _)) if vesting < balance_due => vesting,
+			Some(_) => balance_due,
+			None => Zero::zero(),
+		}
+	}
+
+	// Remove the claim of `signer`, which must have been validated to be `balance_due`, on
+	// behalf of `dest`, who it is to pay `payout`. The part of it which vests is set aside for
+	// `dest`, and returned; the rest is for the caller to credit.
+	fn take_claim(signer: &EthereumAddress, dest: &T::AccountId, balance_due: BalanceOf<T>, payout: &Payout<T>)
+		-> BalanceOf<T>
+	{
+		Self::note_claim(dest);
+		Self::note_claimed_by(dest, balance_due);
+		<ClaimedAddresses<T>>::insert(signer, true);
+
+		let vesting = Self::vesting_part(signer, balance_due);
+		// The schedule is kept along with `History`, so that `reverse_claim` can restore it.
+		let schedule = if Self::keep_history() { Self::vesting(signer) } else { <Vesting<T>>::take(signer) };
+		if let Some((_, at)) = schedule {
+			if !vesting.is_zero() {
+				<Vested<T>>::mutate(dest, |v| v.push((vesting, at)));
+			}
+		}
+
+		if Self::keep_history() {
+			let now = <system::Module<T>>::block_number();
+			<History<T>>::insert(signer, (dest.clone(), balance_due, now));
+			// A merged batch tops up each destination once rather than per claim, so its top-up
+			// is left out here and stays with `dest` if the claim is reversed.
+			<PaidOut<T>>::insert(signer, (payout.liquid, payout.top_up, payout.bonus));
+		}
+
+		if <Claims<T>>::take(signer).is_some() {
+			// The category is kept along with `History`, so that `reverse_claim` can restore it.
+			let category = if Self::keep_history() {
+				Self::claim_category(signer)
+			} else {
+				<ClaimCategory<T>>::take(signer)
+			};
+			if let Some(category) = category {
+				Self::reduce_category_total(&category, balance_due);
+			}
+			Self::reduce_total(balance_due);
+		} else if Self::child_claim(signer).is_some() {
+			sr_io::clear_child_storage(CHILD_STORAGE_KEY, signer);
+			Self::reduce_total(balance_due);
+		} else {
+			// A foreign claim, which `Total` doesn't cover.
+			<ForeignClaims<T>>::remove(signer);
+			<TotalClaimed<T>>::mutate(|t| *t += balance_due);
+		}
+
+		vesting
+	}
+
+	// Credit `dest` with `amount` of the labeled allocation of `signer`, leaving `remaining`.
+	fn take_labeled(
+		signer: &EthereumAddress,
+		dest: &T::AccountId,
+		remaining: Vec<(Vec<u8>, BalanceOf<T>)>,
+		amount: BalanceOf<T>
+	) -> Result {
+		let payout = Self::plan_payout(dest, amount, Zero::zero(), None, true, &Pending::new())?;
+
+		if remaining.is_empty() {
+			<LabeledClaims<T>>::remove(signer);
+			<ClaimedAddresses<T>>::insert(signer, true);
+		} else {
+			<LabeledClaims<T>>::insert(signer, remaining);
+		}
+		Self::note_claim(dest);
+		Self::note_claimed_by(dest, amount);
+		Self::reduce_total(amount);
+		let created = Self::is_new_account(dest);
+		let (total, credited) = Self::pay_out(signer, dest, &payout, None)?;
+
+		Self::deposit_event(RawEvent::Claimed(
+			dest.clone(), *signer, total, credited, <system::Module<T>>::block_number(), created
+		));
+		Ok(())
+	}
+
+	// Count a claim made by `dest`.
+	fn note_claim(dest: &T::AccountId) {
+		<ClaimsCount<T>>::mutate(|c| *c += 1);
+		if !Self::has_claimed(dest) {
+			<ClaimedAccounts<T>>::insert(dest, true);
+			<TotalOfClaimedAccounts<T>>::mutate(|c| *c += 1);
+		}
+	}
+
+	// Add `amount` to what `dest` has been credited over all of its claims.
+	fn note_claimed_by(dest: &T::AccountId, amount: BalanceOf<T>) {
+		<ClaimedBy<T>>::mutate(dest, |c| *c += amount);
+	}
+
+	// Undo `note_claim` and `note_claimed_by` for a claim of `amount` by `dest` which has been
+	// reversed.
+	fn unnote_claim(dest: &T::AccountId, amount: BalanceOf<T>) {
+		<ClaimsCount<T>>::mutate(|c| *c -= 1);
+		<ClaimedBy<T>>::mutate(dest, |c| *c -= amount);
+		if Self::claimed_by(dest).is_zero() && Self::has_claimed(dest) {
+			<ClaimedAccounts<T>>::remove(dest);
+			<TotalOfClaimedAccounts<T>>::mutate(|c| *c -= 1);
+		}
+	}
+
+	// Set `Total` outside of a claim, recording the change for auditors.
+	fn set_total(total: BalanceOf<T>, reason: TotalChange) {
+		let old = Self::total();
+		<Total<T>>::put(total);
+		if old != total {
+			Self::deposit_event(RawEvent::TotalChanged(old, total, reason));
+		}
+	}
+
+	// Take `amount` out of the total of `category`, dropping it once nothing is left of it.
+	fn reduce_category_total(category: &Category, amount: BalanceOf<T>) {
+		let total = Self::total_by_category(category) - amount;
+		if total.is_zero() {
+			<TotalByCategory<T>>::remove(category);
+		} else {
+			<TotalByCategory<T>>::insert(category, total);
+		}
+	}
+
+	// Take `amount` of a claim which has been made out of `Total`.
+	fn reduce_total(amount: BalanceOf<T>) {
+		<Total<T>>::mutate(|t| if *t < amount {
+			panic!("Logic error: Pot less than the total of claims!")
+		} else {
+			*t -= amount
+		});
+		<TotalClaimed<T>>::mutate(|t| *t += amount);
+
+		let total = Self::total();
+		Self::deposit_event(RawEvent::TotalUpdated(total));
+		if total.is_zero() {
+			Self::deposit_event(RawEvent::AllClaimsCompleted);
+		}
+	}
+
+	// What `dest` needs on top of `value` to reach the existential deposit once `pending` is
+	// paid out, if topping up is enabled and what is left of `TopUpPot` can cover it.
+	fn top_up_due(dest: &T::AccountId, value: BalanceOf<T>, pending: &Pending<T>) -> BalanceOf<T> {
+		if !Self::top_up_to_ed() {
+			return Zero::zero();
+		}
+
+		let balance = T::Currency::free_balance(dest) + pending.credited(dest) + value;
+		let existential_deposit = T::Currency::existential_deposit();
+		if balance >= existential_deposit {
+			return Zero::zero();
+		}
+
+		let shortfall = existential_deposit - balance;
+		if shortfall > Self::top_up_pot() - pending.top_up {
+			return Zero::zero();
+		}
+		shortfall
+	}
+
+	// The bonus due on a claim of `amount` made now, out of what is left of `BonusPool` once
+	// `pending` is paid out.
+	fn bonus_due(amount: BalanceOf<T>, pending: &Pending<T>) -> BalanceOf<T> {
+		let now = <system::Module<T>>::block_number();
+		let rate = match Self::bonus_tiers().into_iter().find(|&(deadline, _)| now < deadline) {
+			Some((_, rate)) => rate,
+			None => return Zero::zero(),
+		};
+
+		rstd::cmp::min(perbill_of(rate, amount), Self::bonus_pool() - pending.bonus)
+	}
+
+	// Whether `who` already holds more than `MaxPriorBalanceForClaim`, once `pending` is paid
+	// out.
+	fn is_rich(who: &T::AccountId, pending: &Pending<T>) -> bool {
+		let max = Self::max_prior_balance_for_claim();
+		!max.is_zero() && T::Currency::free_balance(who) + pending.credited(who) > max
+	}
+
+	// What `who` is paid of the liquid `amount` of a claim: all of it, unless it is already
+	// rich, when only `RichClaimRate` of it is.
+	fn rich_part(who: &T::AccountId, amount: BalanceOf<T>, pending: &Pending<T>) -> BalanceOf<T> {
+		if Self::is_rich(who, pending) {
+			perbill_of(Self::rich_claim_rate(), amount)
+		} else {
+			amount
+		}
+	}
+
+	// Whether `who` has no balance yet, so crediting it creates the account.
+	fn is_new_account(who: &T::AccountId) -> bool {
+		T::Currency::free_balance(who).is_zero()
+	}
+
+	// Check that the reserve holds `amount`, if claims are paid out of it.
+	fn ensure_reserve_covers(amount: BalanceOf<T>) -> Result {
+		ensure!(
+			!Self::pay_from_reserve() || T::Currency::free_balance(&Self::reserve_account()) >= amount,
+			"Claims reserve has too little to pay the claim"
+		);
+		Ok(())
+	}
+
+	// Credit `value` to `who`: out of the reserve if `PayFromReserve` is set, and minted
+	// otherwise. Nothing is minted in place of what the reserve lacks: this fails, changing
+	// nothing, if it holds less than `value`. Claims check with `plan_payout` that it holds
+	// all they pay out before writing anything, so their credits don't fail.
+	fn credit(who: &T::AccountId, value: BalanceOf<T>) -> Result {
+		if Self::pay_from_reserve() {
+			T::Currency::withdraw(&Self::reserve_account(), value)?;
+		}
+		T::Currency::deposit_creating(who, value);
+		Ok(())
+	}
+}
+
+/// The payment of transaction fees through `P`, except that an account with no free balance
+/// defers its fee, to be taken out of what its `claim` credits, so it can claim without first
+/// being sent the fee. A fee which no claim pays by the end of the block is forgiven, so the
+/// transaction pool should admit nothing but `claim` from such accounts.
+pub struct FeeFromClaim<T, P>(PhantomData<(T, P)>);
+
+impl<T: Trait, P: MakePayment<T::AccountId>> MakePayment<T::AccountId> for FeeFromClaim<T, P> {
+	fn make_payment(who: &T::AccountId, encoded_len: usize) -> Result {
+		if !T::Currency::free_balance(who).is_zero() {
+			return P::make_payment(who, encoded_len);
+		}
+		<DeferredFees<T>>::mutate(|fees| fees.push((who.clone(), T::TransactionFee::fee(encoded_len))));
+		Ok(())
+	}
+}
+
+/// A check of claims as they enter the transaction pool, so that claims which can only fail are
+/// dropped there rather than taking up a block.
+///
+/// It is kept light, as it runs on every transaction the pool is given: only `claim`,
+/// `claim_batch`, `claim_next_chunk`, `claim_label` and `claim_ss58_digest` are checked, for
+/// signatures which recover to addresses with something left for the call to claim.
+pub struct ClaimPrevalidator<T>(PhantomData<T>);
+
+impl<T: Trait> ClaimPrevalidator<T> {
+	/// Check the call `call` signed by `who`.
+	pub fn validate(who: &T::AccountId, call: &Call<T>) -> Result {
+		match *call {
+			Call::claim(ref ethereum_signature) => {
+				Self::ensure_has_claim(<Module<T>>::recover_signer(ethereum_signature, who, &[])?)
+			}
+			Call::claim_batch(ref claims, _) => {
+				<Module<T>>::ensure_signature_count(claims.len())?;
+				for (dest, ethereum_signature) in claims {
+					Self::ensure_has_claim(<Module<T>>::recover_signer(ethereum_signature, dest, &[])?)?;
+				}
+				Ok(())
+			}
+			Call::claim_next_chunk(ref ethereum_signature) => {
+				let signer = <Module<T>>::recover_signer(ethereum_signature, who, &[])?;
+				ensure!(
+					(<Module<T>>::chunks_claimed(&signer) as usize) < <Module<T>>::chunked_claims(&signer).len(),
+					"Ethereum address has no chunk left to claim"
+				);
+				Ok(())
+			}
+			Call::claim_label(ref ethereum_signature, ref label) => {
+				let signer = <Module<T>>::recover_signer(ethereum_signature, who, &[])?;
+				ensure!(
+					<Module<T>>::labeled_claims(&signer).iter().any(|(l, _)| l == label),
+					"Ethereum address has no claim under the label"
+				);
+				Ok(())
+			}
+			Call::claim_ss58_digest(ref ss58_address, ref ethereum_signature) => {
+				Self::ensure_has_claim(<Module<T>>::recover_ss58_digest(who, ss58_address, ethereum_signature)?)
+			}
+			_ => Ok(()),
+		}
+	}
+
+	fn ensure_has_claim(signer: EthereumAddress) -> Result {
+		ensure!(<Module<T>>::has_claim(&signer), "Ethereum address has no claim");
+		Ok(())
+	}
+}
+
+/// The identifier of the claims migration inherent.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"ethclaim";
+
+/// The data of the claims migration inherent: destination accounts with an Ethereum signature
+/// over each.
+pub type InherentType<AccountId> = Vec<(AccountId, EcdsaSignature)>;
+
+impl<T: Trait> ProvideInherent for Module<T> {
+	type Call = Call<T>;
+	type Error = MakeFatalError<RuntimeString>;
+	const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
+
+	fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+		// The migration is a one-off, so the inherent is only created when the block author
+		// has been given the data for it.
+		data.get_data::<InherentType<T::AccountId>>(&INHERENT_IDENTIFIER)
+			.expect("Claims inherent data could not be decoded.")
+			.map(Call::migrate_claims)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use secp256k1;
+	use tiny_keccak::keccak256;
+	use super::*;
+
+	use sr_io::{self as runtime_io, with_externalities};
+	use substrate_primitives::{H256, Blake2Hasher, hexdisplay::HexDisplay};
+	use codec::{Decode, Encode};
+	// The testing primitives are very useful for avoiding having to work with signatures
+	// or public keys. `u64` is used as the `AccountId` and no `Signature`s are requried.
+	use sr_primitives::{
+		BuildStorage, traits::{BlakeTwo256, IdentityLookup, Lazy, OnInitialise, OnFinalise}, testing::{Digest, DigestItem, Header}
+	};
+	use balances;
+	use std::cell::RefCell;
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	mod claims {
+		// Re-export the module under the name `impl_outer_event!` expects.
+		pub use super::super::*;
+	}
+
+	impl_outer_event! {
+		pub enum TestEvent for Test {
+			balances<T>, claims<T>,
+		}
+	}
+
+	// For testing the module, we construct most of a mock runtime. This means
+	// first constructing a configuration type (`Test`) which `impl`s each of the
+	// configuration traits of modules we want to use.
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = TestLookup;
+		type Header = Header;
+		type Event = TestEvent;
+		type Log = DigestItem;
+	}
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type EnsureAccountLiquid = ();
+		type Event = TestEvent;
+	}
+	impl Trait for Test {
+		type Event = TestEvent;
+		type CoSignature = TestSignature;
+		type Currency = Balances;
+		type Processor = (FreezeCheck<Test>, AmountBounds<Test>, RejectAddress, AccountAgeCheck<Test, TestAccountAge>);
+		type ForeignRate = TestRate;
+		type TransactionFee = Balances;
+		type AddressDerivation = TestDerivation;
+		type PreHashing = Blake2PreHashing;
+		type Identity = TestIdentity;
+		type Teleport = TestTeleport;
+		type WebAuthn = TestWebAuthn;
+	}
+
+	thread_local! {
+		static DISPLAY_NAMES: RefCell<Vec<(u64, Vec<u8>)>> = RefCell::new(vec![]);
+	}
+
+	// Records display names in `DISPLAY_NAMES`, allowing only those of 1 to 32 bytes.
+	pub struct TestIdentity;
+	impl Identity<u64> for TestIdentity {
+		fn can_set_display_name(_: &u64, name: &[u8]) -> Result {
+			ensure!(!name.is_empty() && name.len() <= 32, "Invalid display name");
+			Ok(())
+		}
+
+		fn set_display_name(who: &u64, name: Vec<u8>) {
+			DISPLAY_NAMES.with(|n| n.borrow_mut().push((*who, name)));
+		}
+	}
+
+	thread_local! {
+		static TELEPORTS: RefCell<Vec<(MultiLocation, u64)>> = RefCell::new(vec![]);
+	}
+
+	// Records teleports in `TELEPORTS`, allowing only those to parachain 100.
+	pub struct TestTeleport;
+	impl Teleport<u64> for TestTeleport {
+		fn can_teleport(dest: &MultiLocation) -> Result {
+			match *dest {
+				MultiLocation::ParachainAccount(100, _) => Ok(()),
+				_ => Err("Unknown parachain"),
+			}
+		}
+
+		fn teleport(dest: &MultiLocation, amount: u64) {
+			TELEPORTS.with(|t| t.borrow_mut().push((dest.clone(), amount)));
+		}
+	}
+
+	// Takes an assertion as signed if its signature is the Keccak-256 hash of the public key,
+	// the authenticator data and the client data, twice, standing in for P-256.
+	pub struct TestWebAuthn;
+	impl WebAuthnVerifier for TestWebAuthn {
+		fn verify_assertion(
+			public_key: &[u8; 64],
+			authenticator_data: &[u8],
+			client_data_json: &[u8],
+			signature: &[u8; 64]
+		) -> bool {
+			let hash = keccak256(&[&public_key[..], authenticator_data, client_data_json].concat());
+			&signature[..32] == &hash[..] && &signature[32..] == &hash[..]
+		}
+	}
+
+	thread_local! {
+		static RATE: RefCell<Option<u64>> = RefCell::new(None);
+	}
+
+	// A rate oracle quoting whatever is in `RATE`.
+	pub struct TestRate;
+	impl RateOracle<u64> for TestRate {
+		fn rate() -> Option<u64> {
+			RATE.with(|r| *r.borrow())
+		}
+	}
+
+	thread_local! {
+		static FULL_KEY_DERIVATION: RefCell<bool> = RefCell::new(false);
+	}
+
+	// Derives addresses as Ethereum does, or from the hash of the key with its `0x04` prefix
+	// while `FULL_KEY_DERIVATION` is set.
+	pub struct TestDerivation;
+	impl AddressDerivation for TestDerivation {
+		fn derive_address(pubkey: &[u8; 64]) -> EthereumAddress {
+			if !FULL_KEY_DERIVATION.with(|f| *f.borrow()) {
+				return <() as AddressDerivation>::derive_address(pubkey);
+			}
+			let mut key = vec![4u8];
+			key.extend_from_slice(&pubkey[..]);
+			let mut res = EthereumAddress::default();
+			res.copy_from_slice(&keccak256(&key)[12..]);
+			res
+		}
+	}
+
+	thread_local! {
+		static REJECTED: RefCell<Option<EthereumAddress>> = RefCell::new(None);
+	}
+
+	thread_local! {
+		static CREATED_AT: RefCell<Vec<(u64, u64)>> = RefCell::new(vec![]);
+	}
+
+	// Accounts are created at the blocks listed in `CREATED_AT`.
+	pub struct TestAccountAge;
+	impl AccountAge<u64, u64> for TestAccountAge {
+		fn created_at(who: &u64) -> Option<u64> {
+			CREATED_AT.with(|c| c.borrow().iter().find(|&&(w, _)| w == *who).map(|&(_, n)| n))
+		}
+	}
+
+	// Rejects the claim of whichever address is in `REJECTED`.
+	pub struct RejectAddress;
+	impl ClaimProcessor<u64, u64> for RejectAddress {
+		fn pre_claim(_: &u64, signer: &EthereumAddress, _: u64) -> Result {
+			ensure!(REJECTED.with(|r| *r.borrow() != Some(*signer)), "Ethereum address rejected");
+			Ok(())
+		}
+	}
+
+	// Addresses which are either an account ID or an index resolving to account `40 + index`.
+	#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode)]
+	pub enum TestAddress {
+		Id(u64),
+		Index(u32),
+	}
+	pub struct TestLookup;
+	impl StaticLookup for TestLookup {
+		type Source = TestAddress;
+		type Target = u64;
+		fn lookup(a: TestAddress) -> result::Result<u64, &'static str> {
+			match a {
+				TestAddress::Id(id) => Ok(id),
+				TestAddress::Index(index) if index < 10 => Ok(40 + index as u64),
+				TestAddress::Index(_) => Err("Unknown account index"),
+			}
+		}
+		fn unlookup(id: u64) -> TestAddress {
+			TestAddress::Id(id)
+		}
+	}
+
+	// A signature which is valid for exactly one signer and message.
+	#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode)]
+	pub struct TestSignature(u64, Vec<u8>);
+	impl Verify for TestSignature {
+		type Signer = u64;
+		fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &u64) -> bool {
+			*signer == self.0 && msg.get() == &self.1[..]
+		}
+	}
+	type System = system::Module<Test>;
+	type Balances = balances::Module<Test>;
+	type Claims = Module<Test>;
+
+	// The events deposited by this module so far.
+	fn claims_events() -> Vec<Event<Test>> {
+		System::events().into_iter().filter_map(|r| match r.event {
+			TestEvent::claims(e) => Some(e),
+			_ => None,
+		}).collect()
+	}
+
+	// A second mock runtime, paying claims out through a currency which only records deposits.
+	impl_outer_origin! {
+		pub enum MockOrigin for MockTest {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct MockTest;
+	impl system::Trait for MockTest {
+		type Origin = MockOrigin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<u64>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+	impl Trait for MockTest {
+		type Event = ();
+		type CoSignature = TestSignature;
+		type Currency = MockCurrency;
+		type Processor = ();
+		type ForeignRate = ();
+		type TransactionFee = ();
+		type AddressDerivation = ();
+		type PreHashing = ();
+		type Identity = ();
+		type Teleport = ();
+		type WebAuthn = ();
+	}
+	type MockClaims = Module<MockTest>;
+
+	thread_local! {
+		static DEPOSITS: RefCell<Vec<(u64, u64)>> = RefCell::new(vec![]);
+	}
+
+	pub struct MockCurrency;
+	impl Currency<u64> for MockCurrency {
+		type Balance = u64;
+
+		fn free_balance(who: &u64) -> u64 {
+			DEPOSITS.with(|d| d.borrow().iter().filter(|&&(w, _)| w == *who).map(|&(_, v)| v).sum())
+		}
+
+		fn existential_deposit() -> u64 {
+			0
+		}
+
+		fn deposit_creating(who: &u64, value: u64) {
+			DEPOSITS.with(|d| d.borrow_mut().push((*who, value)));
+		}
+
+		fn withdraw(_: &u64, _: u64) -> Result {
+			Err("MockCurrency only records deposits")
+		}
+	}
+
+	fn new_mock_ext(claims: Vec<(EthereumAddress, u64)>) -> sr_io::TestExternalities<Blake2Hasher> {
+		let mut t = system::GenesisConfig::<MockTest>::default().build_storage().unwrap().0;
+		t.extend(GenesisConfig::<MockTest>{
+			claims,
+			..Default::default()
+		}.build_storage().unwrap().0);
+		t.into()
+	}
+
+	fn alice_secret() -> secp256k1::SecretKey {
+		secp256k1::SecretKey::parse(&keccak256(b"Alice")).unwrap()
+	}
+	fn alice_public() -> secp256k1::PublicKey {
+		secp256k1::PublicKey::from_secret_key(&alice_secret())
+	}
+	fn alice_eth() -> EthereumAddress {
+		let mut res = EthereumAddress::default();
+		res.copy_from_slice(&keccak256(&alice_public().serialize()[1..65])[12..]);
+		res
+	}
+	fn alice_sig(who: &[u8]) -> EcdsaSignature {
+		sign_message(&alice_secret(), &create_msg(who))
+	}
+	fn bob_secret() -> secp256k1::SecretKey {
+		secp256k1::SecretKey::parse(&keccak256(b"Bob")).unwrap()
+	}
+	fn bob_public() -> secp256k1::PublicKey {
+		secp256k1::PublicKey::from_secret_key(&bob_secret())
+	}
+	fn bob_eth() -> EthereumAddress {
+		let mut res = EthereumAddress::default();
+		res.copy_from_slice(&keccak256(&bob_public().serialize()[1..65])[12..]);
+		res
+	}
+	fn bob_sig(who: &[u8]) -> EcdsaSignature {
+		sign_message(&bob_secret(), &create_msg(who))
+	}
+	fn charlie_secret() -> secp256k1::SecretKey {
+		secp256k1::SecretKey::parse(&keccak256(b"Charlie")).unwrap()
+	}
+	fn charlie_public() -> secp256k1::PublicKey {
+		secp256k1::PublicKey::from_secret_key(&charlie_secret())
+	}
+	fn charlie_eth() -> EthereumAddress {
+		let mut res = EthereumAddress::default();
+		res.copy_from_slice(&keccak256(&charlie_public().serialize()[1..65])[12..]);
+		res
+	}
+	fn charlie_sig(who: &[u8]) -> EcdsaSignature {
+		sign_message(&charlie_secret(), &create_msg(who))
+	}
+	fn sign_message(secret: &secp256k1::SecretKey, message: &[u8]) -> EcdsaSignature {
+		let msg = keccak256(message);
+		let (sig, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&msg), secret).unwrap();
+		let sig: ([u8; 32], [u8; 32]) = Decode::decode(&mut &sig.serialize()[..]).unwrap();
+		(sig.0, sig.1, RecoveryId::new(recovery_id.serialize()).unwrap())
+	}
+
+	// This function basically just builds a genesis storage key/value store according to
+	// our desired mockup.
+	fn new_test_ext() -> sr_io::TestExternalities<Blake2Hasher> {
+		new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			..Default::default()
+		})
+	}
+
+	fn new_test_ext_with(config: GenesisConfig<Test>) -> sr_io::TestExternalities<Blake2Hasher> {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		// We use default for brevity, but you can configure as desired if needed.
+		t.extend(balances::GenesisConfig::<Test>::default().build_storage().unwrap().0);
+		t.extend(config.build_storage().unwrap().0);
+		t.into()
+	}
+
+	#[test]
+	fn basic_setup_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(Claims::total(), 100);
+			assert_eq!(Claims::claims(&alice_eth()), Some(100));
+			assert_eq!(Claims::claims(&[0; 20]), None);
+		});
+	}
+
+	#[test]
+	fn storage_defaults_without_genesis_work() {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test>::default().build_storage().unwrap().0);
+		with_externalities(&mut t.into(), || {
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(Claims::claims(&alice_eth()), None);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+		});
+	}
+
+	#[test]
+	fn zero_genesis_claims_are_dropped() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 0)],
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::total(), 100);
+			assert_eq!(Claims::claims(&bob_eth()), None);
+			assert_noop!(
+				Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())),
+				"Ethereum address has no claim"
+			);
+		});
+	}
+
+	#[test]
+	fn computed_total_matches_genesis() {
+		let claims = vec![(alice_eth(), 100), (bob_eth(), 200), (charlie_eth(), 0)];
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: claims.clone(),
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::compute_total(&claims), 300);
+			assert_eq!(Claims::compute_total(&claims), Claims::total());
+		});
+		assert_eq!(Claims::compute_total(&[]), 0);
+	}
+
+	#[test]
+	fn export_claims_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::export_claims(), vec![(alice_eth(), 100), (bob_eth(), 200)]);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::export_claims(), vec![(bob_eth(), 200)]);
+		});
+	}
+
+	#[test]
+	fn address_derivation_is_pluggable() {
+		let mut full_key = EthereumAddress::default();
+		full_key.copy_from_slice(&keccak256(&alice_public().serialize()[..])[12..]);
+		assert!(full_key != alice_eth());
+
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(full_key, 100)],
+			..Default::default()
+		}), || {
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+			FULL_KEY_DERIVATION.with(|f| *f.borrow_mut() = true);
+			let claimed = Claims::claim(Origin::signed(42), alice_sig(&42u64.encode()));
+			FULL_KEY_DERIVATION.with(|f| *f.borrow_mut() = false);
+			assert_ok!(claimed);
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn claiming_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn original_claims_outlast_claiming() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::claims(&alice_eth()), None);
+			assert_eq!(Claims::original_claim(&alice_eth()), 100);
+			assert_eq!(Claims::original_claim(&bob_eth()), 200);
+			assert_eq!(Claims::original_claim(&charlie_eth()), 0);
+		});
+	}
+
+	#[test]
+	fn double_claiming_doesnt_work() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_noop!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())), "Ethereum address has no claim");
+		});
+	}
+
+	#[test]
+	fn non_sender_sig_doesnt_work() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_noop!(Claims::claim(Origin::signed(42), alice_sig(&69u64.encode())), "Ethereum address has no claim");
+		});
+	}
+
+	#[test]
+	fn non_claimant_doesnt_work() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_noop!(Claims::claim(Origin::signed(42), bob_sig(&69u64.encode())), "Ethereum address has no claim");
+		});
+	}
+
+	#[test]
+	fn failed_claims_have_no_side_effects() {
+		with_externalities(&mut new_test_ext(), || {
+			let root = sr_io::storage_root();
+			assert_eq!(
+				Claims::claim(Origin::signed(42), ([0; 32], [0; 32], RecoveryId::default())),
+				Err("Invalid Ethereum signature")
+			);
+			assert_eq!(
+				Claims::claim(Origin::signed(42), bob_sig(&42u64.encode())),
+				Err("Ethereum address has no claim")
+			);
+			assert!(System::events().is_empty());
+			assert_eq!(sr_io::storage_root(), root);
+			assert_eq!(Claims::claims(&alice_eth()), Some(100));
+			assert_eq!(Claims::total(), 100);
+			assert_eq!(Balances::free_balance(&42), 0);
+		});
+	}
+
+	#[test]
+	fn frontrunning_claims_are_reported() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			keep_history: true,
+			..Default::default()
+		}), || {
+			System::set_block_number(1);
+			assert_ok!(Claims::claim(Origin::signed(69), alice_sig(&69u64.encode())));
+			assert_eq!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				Err("Ethereum address has no claim")
+			);
+			assert_eq!(
+				claims_events().last(),
+				Some(&RawEvent::ClaimFrontrunDetected(42, alice_eth(), 69))
+			);
+
+			System::set_block_number(2);
+			assert!(Claims::claim(Origin::signed(7), alice_sig(&7u64.encode())).is_err());
+			assert_eq!(
+				claims_events().last(),
+				Some(&RawEvent::ClaimFrontrunDetected(7, alice_eth(), 69))
+			);
+
+			// Long after the claim, or when it's the claimant trying again, nothing is reported.
+			System::set_block_number(3);
+			let events = claims_events().len();
+			assert!(Claims::claim(Origin::signed(8), alice_sig(&8u64.encode())).is_err());
+			assert!(Claims::claim(Origin::signed(69), alice_sig(&69u64.encode())).is_err());
+			assert_eq!(claims_events().len(), events);
+		});
+	}
+
+	#[test]
+	fn cosigned_claiming_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::set_co_signer(alice_eth(), Some(7)));
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address requires a co-signature"
+			);
+			assert_noop!(
+				Claims::claim_cosigned(
+					Origin::signed(42),
+					alice_sig(&42u64.encode()),
+					TestSignature(8, (42u64, alice_eth()).encode()),
+				),
+				"Invalid co-signature"
+			);
+			assert_ok!(Claims::claim_cosigned(
+				Origin::signed(42),
+				alice_sig(&42u64.encode()),
+				TestSignature(7, (42u64, alice_eth()).encode()),
+			));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn cosigned_claiming_requires_co_signer() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(
+				Claims::claim_cosigned(
+					Origin::signed(42),
+					alice_sig(&42u64.encode()),
+					TestSignature(7, (42u64, alice_eth()).encode()),
+				),
+				"Ethereum address has no co-signer"
+			);
+		});
+	}
+
+	#[test]
+	fn frozen_claim_doesnt_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::freeze_claim(alice_eth(), true));
+			assert!(Claims::is_frozen(&alice_eth()));
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address claim is frozen"
+			);
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 200);
+
+			assert_ok!(Claims::freeze_claim(alice_eth(), false));
+			assert!(!Claims::is_frozen(&alice_eth()));
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn custom_processor_can_reject_claims() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			REJECTED.with(|r| *r.borrow_mut() = Some(alice_eth()));
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address rejected"
+			);
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 200);
+		});
+	}
+
+	#[test]
+	fn claims_outside_amount_bounds_dont_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			min_claim: 150,
+			..Default::default()
+		}), || {
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Claim is below the minimum"
+			);
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+		});
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			max_claim: 150,
+			..Default::default()
+		}), || {
+			assert_noop!(
+				Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())),
+				"Claim is above the maximum"
+			);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+		});
+	}
+
+	#[test]
+	fn foreign_claims_are_converted_at_the_current_rate() {
+		let config = || GenesisConfig::<Test>{
+			foreign_claims: vec![(alice_eth(), 10), (bob_eth(), 20)],
+			..Default::default()
+		};
+		with_externalities(&mut new_test_ext_with(config()), || {
+			assert_eq!(Claims::total(), 0);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"No rate to convert the foreign claim"
+			);
+
+			RATE.with(|r| *r.borrow_mut() = Some(3));
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 30);
+			assert_eq!(Claims::foreign_claims(&alice_eth()), None);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+		});
+		with_externalities(&mut new_test_ext_with(config()), || {
+			RATE.with(|r| *r.borrow_mut() = Some(5));
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 50);
+		});
+	}
+
+	#[test]
+	fn recovery_account_can_claim_without_ethereum_signature() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			recovery_accounts: vec![(alice_eth(), 42)],
+			..Default::default()
+		}), || {
+			assert_noop!(
+				Claims::claim_signed_by_substrate(Origin::signed(69), alice_eth()),
+				"Sender is not the recovery account of the Ethereum address"
+			);
+			assert_ok!(Claims::claim_signed_by_substrate(Origin::signed(42), alice_eth()));
+			assert_eq!(Balances::free_balance(&42), 100);
+
+			assert_noop!(
+				Claims::claim_signed_by_substrate(Origin::signed(69), bob_eth()),
+				"Sender is not the recovery account of the Ethereum address"
+			);
+			assert_ok!(Claims::set_recovery(bob_eth(), Some(69)));
+			assert_ok!(Claims::claim_signed_by_substrate(Origin::signed(69), bob_eth()));
+			assert_eq!(Balances::free_balance(&69), 200);
+		});
+	}
+
+	#[test]
+	fn message_prefixes_are_those_of_create_msg() {
+		let mut expected = b"\x19Ethereum Signed Message:\n".to_vec();
+		expected.extend_from_slice(b"35");
+		expected.extend_from_slice(CLAIM_MESSAGE_PREFIX);
+		expected.extend_from_slice(b"42");
+		assert_eq!(create_msg(b"42"), expected);
+		assert_eq!(CLAIM_MESSAGE_PREFIX, &b"Pay DOTs to the Polkadot account:"[..]);
+	}
+
+	#[test]
+	fn round_tags_work() {
+		assert_eq!(round_tag(0), Vec::<u8>::new());
+		assert_eq!(round_tag(1), b" (round 1)".to_vec());
+		assert_eq!(round_tag(10), b" (round 10)".to_vec());
+		assert_eq!(round_tag(u32::max_value()), b" (round 4294967295)".to_vec());
+	}
+
+	#[test]
+	fn signatures_of_a_past_round_dont_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			let round_sig = |secret: &secp256k1::SecretKey, round: u32| {
+				let msg = create_msg_with(&TEMPLATES[0], &42u64.encode(), &round_tag(round));
+				sign_message(secret, &msg)
+			};
+			assert_ok!(Claims::advance_round());
+			assert_eq!(Claims::round(), 1);
+			assert_eq!(
+				Claims::claim_message(&42u64.encode()),
+				create_msg_with(&TEMPLATES[0], &42u64.encode(), b" (round 1)")
+			);
+			// Signed without a round, so not for this one.
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+			let bob_round_1 = round_sig(&bob_secret(), 1);
+			assert_ok!(Claims::claim(Origin::signed(42), round_sig(&alice_secret(), 1)));
+			assert_eq!(Balances::free_balance(&42), 100);
+
+			assert_ok!(Claims::advance_round());
+			assert_noop!(
+				Claims::claim(Origin::signed(42), bob_round_1),
+				"Ethereum address has no claim"
+			);
+			assert_ok!(Claims::claim(Origin::signed(42), round_sig(&bob_secret(), 2)));
+			assert_eq!(Balances::free_balance(&42), 300);
+		});
+	}
+
+	#[test]
+	fn message_lengths_are_framed_as_geth_does() {
+		let bare = MessageTemplate { prefix: b"", encoding: PayloadEncoding::Raw };
+		let framed = |length: &[u8], payload: &[u8]| {
+			let mut v = b"\x19Ethereum Signed Message:\n".to_vec();
+			v.extend_from_slice(length);
+			v.extend_from_slice(payload);
+			v
+		};
+
+		assert_eq!(create_msg_with(&bare, &[], &[]), framed(b"0", &[]));
+		assert_eq!(create_msg_with(&bare, &[7; 9], &[]), framed(b"9", &[7; 9]));
+		assert_eq!(create_msg_with(&bare, &[7; 10], &[]), framed(b"10", &[7; 10]));
+		assert_eq!(create_msg_with(&bare, &[7; 99], &[]), framed(b"99", &[7; 99]));
+		assert_eq!(create_msg_with(&bare, &[7; 100], &[]), framed(b"100", &[7; 100]));
+
+		// With the 33-byte claim prefix.
+		let mut payload = CLAIM_MESSAGE_PREFIX.to_vec();
+		payload.extend_from_slice(&[7; 66]);
+		assert_eq!(create_msg(&[7; 66]), framed(b"99", &payload));
+		payload.push(7);
+		assert_eq!(create_msg(&[7; 67]), framed(b"100", &payload));
+	}
+
+	#[test]
+	fn personal_sign_framing_is_that_of_eip191() {
+		assert_eq!(EIP191_PREFIX, 0x19);
+		assert_eq!(PERSONAL_SIGN_VERSION, 0x45);
+		let mut header = vec![EIP191_PREFIX, PERSONAL_SIGN_VERSION];
+		header.extend_from_slice(PERSONAL_SIGN_HEADER);
+		assert_eq!(header, b"\x19Ethereum Signed Message:\n".to_vec());
+		assert!(create_msg(b"42").starts_with(&header));
+	}
+
+	#[test]
+	fn ss58_claim_message_works() {
+		let account = hex!["d172a74cda4c865912c32ba0a80a57ae69abae410e5ccb59dee84e2f4432db4f"];
+		let mut expected = b"\x19Ethereum Signed Message:\n65Pay DOTs to the Polkadot account:".to_vec();
+		expected.extend_from_slice(&account);
+		assert_eq!(
+			ss58_claim_message("5GoKvZWG5ZPYL1WUovuHW3zJBWBP5eT8CbqjdRY4Q6iMaDtZ"),
+			Some(expected)
+		);
+		assert_eq!(ss58_claim_message("5GoKvZWG5ZPYL1WUovuHW3zJBWBP5eT8CbqjdRY4Q6iMaDtY"), None);
+	}
+
+	#[test]
+	fn claiming_chunks_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			chunked_claims: vec![(alice_eth(), vec![100, 100, 50])],
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::total(), 250);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Claims::chunks_claimed(&alice_eth()), 1);
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 200);
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 250);
+			assert_eq!(Claims::total(), 0);
+			assert!(Claims::chunked_claims(&alice_eth()).is_empty());
+
+			assert_noop!(
+				Claims::claim_next_chunk(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no chunk left to claim"
+			);
+		});
+	}
+
+	#[test]
+	fn zero_r_or_s_signatures_dont_work() {
+		with_externalities(&mut new_test_ext(), || {
+			let (r, s, v) = alice_sig(&42u64.encode());
+			for sig in &[([0; 32], [0; 32], RecoveryId::default()), ([0; 32], s, v), (r, [0; 32], v)] {
+				assert_eq!(ecdsa_recover(sig, &keccak256(&create_msg(&42u64.encode()))), None);
+				assert_noop!(Claims::claim(Origin::signed(42), *sig), "Invalid Ethereum signature");
+			}
+		});
+	}
+
+	#[test]
+	fn recoveries_can_be_reproduced_off_chain() {
+		with_externalities(&mut new_test_ext(), || {
+			let who = 42u64.encode();
+			let preimage = Claims::recovery_preimage(&who);
+			assert_eq!(preimage, create_msg(&who));
+			let digest = Claims::recovery_digest(&who);
+			assert_eq!(digest, keccak256(&preimage));
+
+			// Recover the signature over the digest as a client would, without the runtime.
+			let (r, s, v) = alice_sig(&who);
+			let mut rs = [0u8; 64];
+			rs[..32].copy_from_slice(&r);
+			rs[32..].copy_from_slice(&s);
+			let pubkey = secp256k1::recover(
+				&secp256k1::Message::parse(&digest),
+				&secp256k1::Signature::parse_slice(&rs).unwrap(),
+				&secp256k1::RecoveryId::parse(v.index()).unwrap(),
+			).unwrap();
+			assert_eq!(&keccak256(&pubkey.serialize()[1..])[12..], &alice_eth()[..]);
+		});
+	}
+
+	#[test]
+	fn recovery_errors_are_told_apart() {
+		with_externalities(&mut new_test_ext(), || {
+			let who = 42u64.encode();
+			let (_, s, v) = alice_sig(&who);
+			assert_eq!(Claims::diagnose_signature(&who, &alice_sig(&who)), Ok(alice_eth()));
+			assert_eq!(
+				Claims::diagnose_signature(&who, &([0; 32], s, v)),
+				Err(RecoveryError::InvalidSignature)
+			);
+
+			// There's no point on the curve with an x-coordinate of 5.
+			let mut r = [0u8; 32];
+			r[31] = 5;
+			assert_eq!(
+				Claims::diagnose_signature(&who, &(r, s, v)),
+				Err(RecoveryError::NoPublicKey)
+			);
+		});
+	}
+
+	#[test]
+	fn claiming_with_proof_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			let alice_leaf = keccak256(&(0u32, alice_eth(), 42u64).encode());
+			let bob_leaf = keccak256(&(1u32, bob_eth(), 69u64).encode());
+			assert_noop!(
+				Claims::claim_with_proof(Origin::signed(42), 0, alice_eth(), vec![bob_leaf]),
+				"No recovery root is set"
+			);
+			assert_ok!(Claims::set_recovery_root(merkle_root(alice_leaf, &[bob_leaf])));
+
+			// The leaf commits to the account and index as well as the address.
+			assert_noop!(
+				Claims::claim_with_proof(Origin::signed(43), 0, alice_eth(), vec![bob_leaf]),
+				"Invalid recovery proof"
+			);
+			assert_noop!(
+				Claims::claim_with_proof(Origin::signed(42), 1, alice_eth(), vec![bob_leaf]),
+				"Invalid recovery proof"
+			);
+			assert_noop!(
+				Claims::claim_with_proof(Origin::signed(42), 0, alice_eth(), vec![alice_leaf]),
+				"Invalid recovery proof"
+			);
+
+			assert_ok!(Claims::claim_with_proof(Origin::signed(42), 0, alice_eth(), vec![bob_leaf]));
+			assert_ok!(Claims::claim_with_proof(Origin::signed(69), 1, bob_eth(), vec![alice_leaf]));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Balances::free_balance(&69), 200);
+			assert!(Claims::is_index_claimed(0));
+			assert_noop!(
+				Claims::claim_with_proof(Origin::signed(42), 0, alice_eth(), vec![bob_leaf]),
+				"Leaf index already claimed"
+			);
+		});
+	}
+
+	#[test]
+	fn distinct_claimed_accounts_are_counted() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_ok!(Claims::claim(Origin::signed(42), bob_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 300);
+			assert_eq!(Claims::claims_count(), 2);
+			assert_eq!(Claims::total_of_claimed_accounts(), 1);
+			assert!(Claims::has_claimed(&42));
+			assert!(!Claims::has_claimed(&69));
+		});
+	}
+
+	#[test]
+	fn claiming_with_ss58_digest_works() {
+		with_externalities(&mut new_test_ext(), || {
+			// The SS58 addresses of accounts 42 and 69.
+			let address_42 = b"BTRjp7Wgjtph9sH".to_vec();
+			let address_69 = b"BUwihGSfZoDNCCb".to_vec();
+			assert_eq!(from_base58(&address_42).unwrap()[1..9], 42u64.encode()[..]);
+
+			assert_noop!(
+				Claims::claim_ss58_digest(Origin::signed(42), address_69.clone(), sign_message(&alice_secret(), &address_69)),
+				"SS58 address is not that of the sender"
+			);
+			assert_noop!(
+				Claims::claim_ss58_digest(Origin::signed(42), b"0OIl".to_vec(), sign_message(&alice_secret(), b"0OIl")),
+				"Invalid SS58 address"
+			);
+			assert_ok!(Claims::claim_ss58_digest(Origin::signed(42), address_42.clone(), sign_message(&alice_secret(), &address_42)));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn ss58_digest_signatures_are_bound_to_the_statement_and_round() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			statement: b"I am not a US person".to_vec(),
+			..Default::default()
+		}), || {
+			let address = b"BTRjp7Wgjtph9sH".to_vec();
+			let digest_sig = |secret: &secp256k1::SecretKey, suffix: &[u8]| {
+				sign_message(secret, &[&address[..], suffix].concat())
+			};
+			// Signed over the address alone.
+			assert_noop!(
+				Claims::claim_ss58_digest(Origin::signed(42), address.clone(), digest_sig(&alice_secret(), b"")),
+				"Ethereum address has no claim"
+			);
+
+			assert_ok!(Claims::advance_round());
+			assert_eq!(
+				Claims::ss58_digest_message(&address),
+				[&address[..], &b"I am not a US person (round 1)"[..]].concat()
+			);
+			let bob_round_1 = digest_sig(&bob_secret(), b"I am not a US person (round 1)");
+			assert_ok!(Claims::advance_round());
+			assert_noop!(
+				Claims::claim_ss58_digest(Origin::signed(42), address.clone(), bob_round_1),
+				"Ethereum address has no claim"
+			);
+
+			let sig = digest_sig(&alice_secret(), b"I am not a US person (round 2)");
+			assert_ok!(Claims::claim_ss58_digest(Origin::signed(42), address.clone(), sig));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn batch_claims_are_made_in_order() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200), (charlie_eth(), 300)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim_batch(Origin::signed(1), vec![
+				(69, bob_sig(&69u64.encode())),
+				(7, charlie_sig(&7u64.encode())),
+				(42, alice_sig(&42u64.encode())),
+			], false));
+			assert_eq!(claims_events(), vec![
+				RawEvent::TotalUpdated(400),
+				RawEvent::Claimed(69, bob_eth(), 200, 200, 0, true),
+				RawEvent::TotalUpdated(100),
+				RawEvent::Claimed(7, charlie_eth(), 300, 300, 0, true),
+				RawEvent::TotalUpdated(0),
+				RawEvent::AllClaimsCompleted,
+				RawEvent::Claimed(42, alice_eth(), 100, 100, 0, true),
+			]);
+		});
+	}
+
+	#[test]
+	fn inspect_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let who = 42u64.encode();
+			assert_eq!(Claims::inspect(&who, &alice_sig(&who)), ClaimInfo {
+				recovered_address: Some(alice_eth()),
+				amount: Some(100),
+				is_claimed: false,
+				is_frozen: false,
+			});
+			assert_eq!(Claims::inspect(&who, &bob_sig(&who)), ClaimInfo {
+				recovered_address: Some(bob_eth()),
+				amount: None,
+				is_claimed: false,
+				is_frozen: false,
+			});
+			assert_eq!(Claims::inspect(&who, &([0; 32], [0; 32], RecoveryId::default())), ClaimInfo::default());
+
+			assert_ok!(Claims::freeze_claim(alice_eth(), true));
+			assert!(Claims::inspect(&who, &alice_sig(&who)).is_frozen);
+			assert_ok!(Claims::freeze_claim(alice_eth(), false));
+
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&who)));
+			assert_eq!(Claims::inspect(&who, &alice_sig(&who)), ClaimInfo {
+				recovered_address: Some(alice_eth()),
+				amount: None,
+				is_claimed: true,
+				is_frozen: false,
+			});
+		});
+	}
+
+	#[test]
+	fn claimed_event_carries_the_block_number() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(7);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(claims_events().last(), Some(&RawEvent::Claimed(42, alice_eth(), 100, 100, 7, true)));
+		});
+	}
+
+	#[test]
+	fn claimed_event_tells_whether_the_account_was_created() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(claims_events().last(), Some(&RawEvent::Claimed(42, alice_eth(), 100, 100, 0, true)));
+
+			// The claim of Bob is made to the account Alice's claim created.
+			assert_ok!(Claims::claim(Origin::signed(42), bob_sig(&42u64.encode())));
+			assert_eq!(claims_events().last(), Some(&RawEvent::Claimed(42, bob_eth(), 200, 200, 0, false)));
+			assert_eq!(Balances::free_balance(&42), 300);
+		});
+	}
+
+	#[test]
+	fn vested_claims_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			vesting: vec![(alice_eth(), (40, 10))],
+			..Default::default()
+		}), || {
+			System::set_block_number(1);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(claims_events(), vec![
+				RawEvent::TotalUpdated(200),
+				RawEvent::Claimed(42, alice_eth(), 100, 60, 1, true),
+				RawEvent::TotalUpdated(0),
+				RawEvent::AllClaimsCompleted,
+				RawEvent::Claimed(69, bob_eth(), 200, 200, 1, true),
+			]);
+			assert_eq!(Balances::free_balance(&42), 60);
+			assert_eq!(Claims::vested(&42), vec![(40, 10)]);
+
+			assert_noop!(Claims::unlock_vested(Origin::signed(42)), "No vested funds to release");
+			System::set_block_number(10);
+			assert_ok!(Claims::unlock_vested(Origin::signed(42)));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert!(Claims::vested(&42).is_empty());
+		});
+	}
+
+	#[test]
+	fn minting_claims_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::mint_claim(bob_eth(), 200));
+			assert_ok!(Claims::mint_claim(alice_eth(), 50));
+			assert_eq!(Claims::total(), 350);
+			assert_eq!(Claims::export_claims(), vec![(alice_eth(), 150), (bob_eth(), 200)]);
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 200);
+		});
+	}
+
+	#[test]
+	fn administrative_changes_to_total_are_recorded() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			keep_history: true,
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::mint_claim(bob_eth(), 200));
+			assert_ok!(Claims::import_child_claims(vec![(charlie_eth(), 300)]));
+			assert_ok!(Claims::claim(Origin::signed(43), alice_sig(&43u64.encode())));
+			assert_ok!(Claims::reverse_claim(alice_eth(), 43, 100));
+			assert_eq!(
+				claims_events().into_iter().filter(|e| match e {
+					RawEvent::TotalChanged(..) => true,
+					_ => false,
+				}).collect::<Vec<_>>(),
+				vec![
+					RawEvent::TotalChanged(100, 300, TotalChange::Minted),
+					RawEvent::TotalChanged(300, 600, TotalChange::ImportedToChildTrie),
+					RawEvent::TotalChanged(500, 600, TotalChange::Reversed),
+				]
+			);
+
+			// No change is recorded when nothing changes.
+			let events = claims_events().len();
+			assert_ok!(Claims::revoke_claims(vec![]));
+			assert_eq!(claims_events()[events..], [RawEvent::ClaimsRevoked(vec![])]);
+		});
+	}
+
+	#[test]
+	fn claiming_minted_claims_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::mint_claim(bob_eth(), 200));
+			assert_eq!(Claims::total(), 300);
+			assert_eq!(Claims::total_claimed(), 0);
+
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 200);
+			assert_eq!(Claims::total(), 100);
+			assert_eq!(Claims::total_claimed(), 200);
+
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(Claims::total_claimed(), 300);
+		});
+	}
+
+	#[test]
+	fn minting_claims_cannot_overflow_total() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), u64::max_value() - 10)],
+			..Default::default()
+		}), || {
+			assert_noop!(Claims::mint_claim(bob_eth(), 11), "Total of claims would overflow");
+			assert_ok!(Claims::mint_claim(bob_eth(), 10));
+			assert_eq!(Claims::total(), u64::max_value());
+		});
+	}
+
+	#[test]
+	fn credited_amount_is_in_the_claimed_event() {
+		// Dispatch results carry no data, so the event is where the credited amount is found.
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(claims_events().last(), Some(&RawEvent::Claimed(42, alice_eth(), 100, 100, 0, true)));
+
+			let sig = bob_sig(&(69u64, 20u64).encode());
+			assert_ok!(Claims::claim_with_fee(Origin::signed(1), 69, sig, 20));
+			assert_eq!(claims_events().last(), Some(&RawEvent::Claimed(69, bob_eth(), 180, 180, 0, true)));
+		});
+	}
+
+	#[test]
+	fn reversing_claims_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			categories: vec![(alice_eth(), b"team".to_vec())],
+			keep_history: true,
+			..Default::default()
+		}), || {
+			assert_noop!(
+				Claims::reverse_claim(alice_eth(), 43, 100),
+				"Ethereum address hasn't been claimed"
+			);
+
+			// The claim is mistakenly made to account 43.
+			assert_ok!(Claims::claim(Origin::signed(43), alice_sig(&43u64.encode())));
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			for &(from, amount) in &[(43, 101), (69, 100)] {
+				assert_noop!(
+					Claims::reverse_claim(alice_eth(), from, amount),
+					"Ethereum address wasn't claimed by that account for that amount"
+				);
+			}
+
+			assert_ok!(Claims::reverse_claim(alice_eth(), 43, 100));
+			assert!(claims_events().contains(&RawEvent::ClaimReversed(43, alice_eth(), 100)));
+			assert_eq!(Balances::free_balance(&43), 0);
+			assert_eq!(Claims::claims(&alice_eth()), Some(100));
+			assert_eq!(Claims::claim_category(&alice_eth()), Some(b"team".to_vec()));
+			assert_eq!(Claims::total(), 100);
+			assert_eq!(Claims::history(&alice_eth()), None);
+			// Alice was listed at genesis, so isn't listed again.
+			assert_eq!(Claims::addresses(), vec![alice_eth(), bob_eth()]);
+			assert!(!Claims::is_claimed(&alice_eth()));
+			assert_eq!(Claims::claims_count(), 1);
+			assert_eq!(Claims::claimed_by(&43), 0);
+			assert!(!Claims::has_claimed(&43));
+			assert_eq!(Claims::total_of_claimed_accounts(), 1);
+			assert_noop!(
+				Claims::reverse_claim(alice_eth(), 43, 100),
+				"Ethereum address still has a claim"
+			);
+
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Claims::claimed_by(&42), 100);
+		});
+	}
+
+	#[test]
+	fn reversing_claims_restores_the_totals() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			categories: vec![(alice_eth(), b"team".to_vec()), (bob_eth(), b"team".to_vec())],
+			vesting: vec![(alice_eth(), (40, 10))],
+			keep_history: true,
+			..Default::default()
+		}), || {
+			let claimed = Claims::total() + Claims::total_claimed();
+			assert_ok!(Claims::claim(Origin::signed(43), alice_sig(&43u64.encode())));
+			assert_ok!(Claims::claim(Origin::signed(43), bob_sig(&43u64.encode())));
+			assert_eq!(Balances::free_balance(&43), 260);
+			assert_eq!(Claims::vested(&43), vec![(40, 10)]);
+			assert_eq!(Claims::total_by_category(b"team".to_vec()), 0);
+
+			assert_ok!(Claims::reverse_claim(alice_eth(), 43, 100));
+			assert_eq!(Balances::free_balance(&43), 200);
+			assert!(Claims::vested(&43).is_empty());
+			assert_eq!(Claims::total(), 100);
+			assert_eq!(Claims::total() + Claims::total_claimed(), claimed);
+			assert_eq!(Claims::total_by_category(b"team".to_vec()), 100);
+
+			// The claim is made again as it first was.
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 60);
+			assert_eq!(Claims::vested(&42), vec![(40, 10)]);
+			assert_eq!(Claims::total_by_category(b"team".to_vec()), 0);
+		});
+	}
+
+	#[test]
+	fn reversing_claims_takes_back_what_was_paid() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			withholding_rate: Perbill::from_billionths(250_000_000),
+			withholding_account: 7,
+			bonus_tiers: vec![(10, Perbill::from_billionths(100_000_000))],
+			bonus_pool: 50,
+			keep_history: true,
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(43), alice_sig(&43u64.encode())));
+			// 75 after withholding, and a bonus of 10.
+			assert_eq!(Balances::free_balance(&43), 85);
+			assert_eq!(Balances::free_balance(&7), 25);
+			assert_eq!(Claims::paid_out(&alice_eth()), (75, 0, 10));
+
+			assert_ok!(Claims::reverse_claim(alice_eth(), 43, 100));
+			assert_eq!(Balances::free_balance(&43), 0);
+			assert_eq!(Balances::free_balance(&7), 25);
+			assert_eq!(Claims::bonus_pool(), 50);
+			assert_eq!(Claims::paid_out(&alice_eth()), (0, 0, 0));
+			assert_eq!(Claims::claims(&alice_eth()), Some(100));
+		});
+	}
+
+	#[test]
+	fn reversing_claims_returns_what_was_paid_to_the_reserve() {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test>{
+			balances: vec![(5, 150)],
+			..Default::default()
+		}.build_storage().unwrap().0);
+		t.extend(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			withholding_rate: Perbill::from_billionths(250_000_000),
+			withholding_account: 7,
+			pay_from_reserve: true,
+			reserve_account: 5,
+			keep_history: true,
+			..Default::default()
+		}.build_storage().unwrap().0);
+		with_externalities(&mut t.into(), || {
+			assert_ok!(Claims::claim(Origin::signed(43), alice_sig(&43u64.encode())));
+			assert_eq!(Balances::free_balance(&43), 75);
+			assert_eq!(Balances::free_balance(&5), 50);
+
+			assert_ok!(Claims::reverse_claim(alice_eth(), 43, 100));
+			assert_eq!(Balances::free_balance(&43), 0);
+			// What was withheld isn't taken back, so the reserve is short of it.
+			assert_eq!(Balances::free_balance(&5), 125);
+			assert_eq!(Balances::free_balance(&7), 25);
+
+			// The claim is made again out of the reserve.
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 75);
+			assert_eq!(Balances::free_balance(&5), 25);
+		});
+	}
+
+	#[test]
+	fn claiming_to_new_accounts_doesnt_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::set_min_account_age(10));
+			CREATED_AT.with(|c| *c.borrow_mut() = vec![(42, 1), (69, 5)]);
+			System::set_block_number(12);
+			assert_noop!(
+				Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())),
+				"Account is too new to claim"
+			);
+			assert_noop!(
+				Claims::claim(Origin::signed(43), alice_sig(&43u64.encode())),
+				"Account is too new to claim"
+			);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn claiming_labels_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			labeled_claims: vec![(alice_eth(), vec![(b"team".to_vec(), 100), (b"bonus".to_vec(), 10)])],
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::total(), 110);
+			assert_noop!(
+				Claims::claim_label(Origin::signed(42), alice_sig(&42u64.encode()), b"advisor".to_vec()),
+				"Ethereum address has no claim under the label"
+			);
+			assert_ok!(Claims::claim_label(Origin::signed(42), alice_sig(&42u64.encode()), b"team".to_vec()));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Claims::labeled_claims(&alice_eth()), vec![(b"bonus".to_vec(), 10)]);
+			assert_noop!(
+				Claims::claim_label(Origin::signed(42), alice_sig(&42u64.encode()), b"team".to_vec()),
+				"Ethereum address has no claim under the label"
+			);
+			assert_ok!(Claims::claim_label(Origin::signed(42), alice_sig(&42u64.encode()), b"bonus".to_vec()));
+			assert_eq!(Balances::free_balance(&42), 110);
+			assert_eq!(Claims::total(), 0);
+		});
+	}
+
+	#[test]
+	fn claiming_all_labels_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			labeled_claims: vec![(alice_eth(), vec![(b"team".to_vec(), 100), (b"bonus".to_vec(), 10)])],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim_all_labels(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 110);
+			assert!(claims_events().contains(&RawEvent::Claimed(42, alice_eth(), 110, 110, 0, true)));
+			assert!(Claims::labeled_claims(&alice_eth()).is_empty());
+			assert_noop!(
+				Claims::claim_all_labels(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no labeled claim"
+			);
+		});
+	}
+
+	#[test]
+	fn bonus_tiers_work() {
+		let config = || GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			bonus_tiers: vec![
+				(10, Perbill::from_billionths(200_000_000)),
+				(20, Perbill::from_billionths(100_000_000)),
+			],
+			bonus_pool: 1000,
+			..Default::default()
+		};
+		for &(block, bonus) in &[(9, 20), (10, 10), (19, 10), (20, 0)] {
+			with_externalities(&mut new_test_ext_with(config()), || {
+				System::set_block_number(block);
+				assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+				assert_eq!(Balances::free_balance(&42), 100 + bonus);
+				assert_eq!(Claims::bonus_pool(), 1000 - bonus);
+				assert_eq!(
+					claims_events().contains(&RawEvent::BonusPaid(42, alice_eth(), bonus)),
+					bonus > 0
+				);
+			});
+		}
+	}
+
+	#[test]
+	fn bonus_is_limited_by_the_pool() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			bonus_tiers: vec![(10, Perbill::from_billionths(200_000_000))],
+			bonus_pool: 15,
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 115);
+			assert_eq!(Claims::bonus_pool(), 0);
+		});
+	}
+
+	#[test]
+	fn withholding_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			withholding_rate: Perbill::from_billionths(250_000_000),
+			withholding_account: 7,
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 75);
+			assert_eq!(Balances::free_balance(&7), 25);
+			assert_eq!(claims_events(), vec![
+				RawEvent::TotalUpdated(0),
+				RawEvent::AllClaimsCompleted,
+				RawEvent::ClaimWithheld(42, alice_eth(), 100, 75),
+				RawEvent::Claimed(42, alice_eth(), 75, 75, 0, true),
+			]);
+		});
+	}
+
+	#[test]
+	fn withholding_applies_to_every_claim_call() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			chunked_claims: vec![(charlie_eth(), vec![100])],
+			labeled_claims: vec![(charlie_eth(), vec![(b"team".to_vec(), 100), (b"bonus".to_vec(), 100)])],
+			withholding_rate: Perbill::from_billionths(250_000_000),
+			withholding_account: 7,
+			referral_rate: Perbill::from_billionths(100_000_000),
+			..Default::default()
+		}), || {
+			// The fee comes out of what is left once a quarter is withheld.
+			assert_noop!(
+				Claims::claim_with_fee(Origin::signed(1), 42, alice_sig(&(42u64, 80u64).encode()), 80),
+				"Fee exceeds the claim"
+			);
+			assert_ok!(Claims::claim_with_fee(Origin::signed(1), 42, alice_sig(&(42u64, 10u64).encode()), 10));
+			assert_eq!(Balances::free_balance(&42), 65);
+			assert_eq!(Balances::free_balance(&1), 10);
+			assert_eq!(Balances::free_balance(&7), 25);
+
+			// As does the referrer's share.
+			assert_ok!(Claims::claim_with_referrer(Origin::signed(69), Some(8), bob_sig(&(69u64, 8u64).encode())));
+			assert_eq!(Balances::free_balance(&69), 135);
+			assert_eq!(Balances::free_balance(&8), 15);
+			assert_eq!(Balances::free_balance(&7), 75);
+
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(43), charlie_sig(&43u64.encode())));
+			assert_eq!(Balances::free_balance(&43), 75);
+			assert_eq!(Balances::free_balance(&7), 100);
+
+			assert_ok!(Claims::claim_label(Origin::signed(43), charlie_sig(&43u64.encode()), b"team".to_vec()));
+			assert_eq!(Balances::free_balance(&43), 150);
+			assert_ok!(Claims::claim_all_labels(Origin::signed(43), charlie_sig(&43u64.encode())));
+			assert_eq!(Balances::free_balance(&43), 225);
+			assert_eq!(Balances::free_balance(&7), 150);
+			assert!(claims_events().contains(&RawEvent::ClaimWithheld(43, charlie_eth(), 100, 75)));
+		});
+	}
+
+	#[test]
+	fn revoking_claims_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200), (charlie_eth(), 300)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::revoke_claims(vec![alice_eth(), alice_eth(), charlie_eth()]));
+			assert_eq!(claims_events(), vec![
+				RawEvent::TotalChanged(600, 200, TotalChange::Revoked),
+				RawEvent::ClaimsRevoked(vec![(alice_eth(), 100), (charlie_eth(), 300)]),
+			]);
+			assert_eq!(Claims::total(), 200);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+			assert_noop!(
+				Claims::claim(Origin::signed(7), charlie_sig(&7u64.encode())),
+				"Ethereum address has no claim"
+			);
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+
+			assert_noop!(
+				Claims::revoke_claims(vec![bob_eth(); MAX_REVOCATIONS_PER_CALL + 1]),
+				"Too many addresses to revoke"
+			);
+		});
+	}
+
+	#[test]
+	fn revoking_claims_revokes_every_kind_of_claim() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			chunked_claims: vec![(bob_eth(), vec![100, 100, 50])],
+			labeled_claims: vec![(charlie_eth(), vec![(b"team".to_vec(), 100)])],
+			foreign_claims: vec![([7; 20], 20)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::import_child_claims(vec![([8; 20], 300)]));
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_ok!(Claims::claim_reserve(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::total(), 650);
+
+			assert_ok!(Claims::revoke_claims(vec![bob_eth(), [8; 20], charlie_eth(), [7; 20], alice_eth()]));
+			assert_eq!(
+				claims_events().last(),
+				Some(&RawEvent::ClaimsRevoked(vec![
+					(bob_eth(), 150), ([8; 20], 300), (charlie_eth(), 100), ([7; 20], 0), (alice_eth(), 100),
+				]))
+			);
+			assert_eq!(Claims::total(), 0);
+			assert!(Claims::chunked_claims(&bob_eth()).is_empty());
+			assert_eq!(Claims::chunks_claimed(&bob_eth()), 0);
+			assert_eq!(Claims::child_claim(&[8; 20]), None);
+			assert!(Claims::labeled_claims(&charlie_eth()).is_empty());
+			assert_eq!(Claims::foreign_claims(&[7; 20]), None);
+			assert_eq!(Claims::reservation(&alice_eth()), None);
+
+			assert_noop!(
+				Claims::claim_next_chunk(Origin::signed(69), bob_sig(&69u64.encode())),
+				"Ethereum address has no chunk left to claim"
+			);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+		});
+	}
+
+	#[test]
+	fn claiming_from_child_trie_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::import_child_claims(vec![(bob_eth(), 200)]));
+			assert_noop!(
+				Claims::import_child_claims(vec![(alice_eth(), 10)]),
+				"Ethereum address already has a claim"
+			);
+			assert_eq!(Claims::child_claim(&bob_eth()), Some(200));
+			assert_eq!(Claims::claims(&bob_eth()), None);
+			assert_eq!(Claims::total(), 300);
+
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 200);
+			assert_eq!(Claims::child_claim(&bob_eth()), None);
+			assert_eq!(Claims::total(), 100);
+			assert_noop!(
+				Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())),
+				"Ethereum address has no claim"
+			);
+		});
+	}
+
+	#[test]
+	fn killing_claims_storage_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200), (charlie_eth(), 305)],
+			rounding_unit: 10,
+			categories: vec![(alice_eth(), b"team".to_vec()), (bob_eth(), b"public".to_vec())],
+			keep_history: true,
+			claims_end: 10,
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_noop!(Claims::kill_claims_storage(10), "Claims are still outstanding");
+			assert_ok!(Claims::claim(Origin::signed(7), charlie_sig(&7u64.encode())));
+
+			System::set_block_number(10);
+			assert_ok!(Claims::sweep_expired(10));
+			assert_eq!(Claims::sweep_cursor(), 3);
+			assert_eq!(Claims::rounding_residual(), (5, 0));
+			System::set_block_number(9);
+			assert_noop!(Claims::kill_claims_storage(10), "Claims have not yet expired");
+			System::set_block_number(10);
+
+			assert_ok!(Claims::kill_claims_storage(2));
+			assert_eq!(Claims::addresses(), vec![alice_eth()]);
+			assert_eq!(Claims::address_at(1), None);
+			assert_eq!(Claims::address_index(&bob_eth()), None);
+			assert!(!Claims::is_claimed(&bob_eth()));
+			assert!(Claims::is_claimed(&alice_eth()));
+			assert_eq!(Claims::total_claimed(), 600);
+
+			assert_ok!(Claims::kill_claims_storage(2));
+			for who in &[alice_eth(), bob_eth(), charlie_eth()] {
+				assert_eq!(Claims::claims(who), None);
+				assert!(!Claims::is_claimed(who));
+				assert_eq!(Claims::history(who), None);
+			}
+			assert_eq!(Claims::addresses(), vec![]);
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(Claims::total_claimed(), 0);
+			assert_eq!(Claims::claims_count(), 0);
+			assert_eq!(Claims::total_of_claimed_accounts(), 0);
+			assert_eq!(Claims::sweep_cursor(), 0);
+			assert_eq!(Claims::rounding_residual(), (0, 0));
+			for category in &[b"team".to_vec(), b"public".to_vec()] {
+				let key = <TotalByCategory<Test>>::key_for(category);
+				assert_eq!(runtime_io::storage(&runtime_io::twox_128(&key)), None);
+			}
+
+			// Once cleared, there's nothing left to do.
+			assert_ok!(Claims::kill_claims_storage(2));
+		});
+	}
+
+	#[test]
+	fn metrics_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::metrics(), (300, 0));
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::metrics(), (200, 1));
+		});
+	}
+
+	#[test]
+	fn claims_of_many_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (charlie_eth(), 300)],
+			..Default::default()
+		}), || {
+			assert_eq!(
+				Claims::claims_of_many(&[charlie_eth(), bob_eth(), alice_eth()]),
+				Ok(vec![Some(300), None, Some(100)])
+			);
+			assert_eq!(Claims::claims_of_many(&[]), Ok(vec![]));
+			assert_eq!(
+				Claims::claims_of_many(&vec![alice_eth(); MAX_ADDRESSES_PER_QUERY + 1]),
+				Err("Too many addresses in one query")
+			);
+		});
+	}
+
+	#[test]
+	fn reserving_claims_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			finalize_delay: 5,
+			..Default::default()
+		}), || {
+			System::set_block_number(1);
+			assert_ok!(Claims::claim_reserve(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::reservation(&alice_eth()), Some((42, 1)));
+			assert_noop!(
+				Claims::claim_reserve(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address claim is already reserved"
+			);
+			assert_noop!(
+				Claims::claim(Origin::signed(69), alice_sig(&69u64.encode())),
+				"Ethereum address claim is reserved for another account"
+			);
+			assert_noop!(
+				Claims::claim_finalize(Origin::signed(69), alice_eth()),
+				"Ethereum address claim is reserved for another account"
+			);
+
+			System::set_block_number(5);
+			assert_noop!(
+				Claims::claim_finalize(Origin::signed(42), alice_eth()),
+				"Reserved claim may not be finalized yet"
+			);
+
+			System::set_block_number(6);
+			assert_ok!(Claims::claim_finalize(Origin::signed(42), alice_eth()));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Claims::reservation(&alice_eth()), None);
+			assert_eq!(Claims::total(), 0);
+		});
+	}
+
+	#[test]
+	fn cancelling_reservations_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			finalize_delay: 5,
+			..Default::default()
+		}), || {
+			System::set_block_number(1);
+			assert_ok!(Claims::claim_reserve(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_noop!(
+				Claims::cancel_reserve(Origin::signed(69), alice_eth()),
+				"Ethereum address claim is reserved for another account"
+			);
+			assert_ok!(Claims::cancel_reserve(Origin::signed(42), alice_eth()));
+			assert_noop!(
+				Claims::cancel_reserve(Origin::signed(42), alice_eth()),
+				"Ethereum address claim is not reserved"
+			);
+
+			// The claim may now be reserved again, here for another account.
+			System::set_block_number(2);
+			assert_ok!(Claims::claim_reserve(Origin::signed(69), alice_sig(&69u64.encode())));
+			assert_eq!(Claims::reservation(&alice_eth()), Some((69, 2)));
+		});
+	}
+
+	#[test]
+	fn claims_per_account_are_limited() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200), (charlie_eth(), 50)],
+			max_per_account: 150,
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::claimed_by(&42), 100);
+			assert_eq!(
+				Claims::claim(Origin::signed(42), bob_sig(&42u64.encode())),
+				Err("Claim would take the account over its limit")
+			);
+			assert_eq!(Claims::claims(&bob_eth()), Some(200));
+			assert_ok!(Claims::claim(Origin::signed(42), charlie_sig(&42u64.encode())));
+			assert_eq!(Claims::claimed_by(&42), 150);
+			assert_eq!(Balances::free_balance(&42), 150);
+		});
+	}
+
+	#[test]
+	fn batch_claims_per_account_are_limited() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 100), (charlie_eth(), 50)],
+			max_per_account: 150,
+			..Default::default()
+		}), || {
+			// Each claim is within the limit, but not both.
+			assert_noop!(
+				Claims::claim_batch(Origin::signed(1), vec![
+					(42, alice_sig(&42u64.encode())),
+					(42, bob_sig(&42u64.encode())),
+				], false),
+				"Claim would take the account over its limit"
+			);
+			assert_eq!(Claims::claimed_by(&42), 0);
+			assert_eq!(Claims::claims(&alice_eth()), Some(100));
+			assert_eq!(Claims::claims(&bob_eth()), Some(100));
+
+			assert_ok!(Claims::claim_batch(Origin::signed(1), vec![
+				(42, alice_sig(&42u64.encode())),
+				(42, charlie_sig(&42u64.encode())),
+			], false));
+			assert_eq!(Claims::claimed_by(&42), 150);
+		});
+	}
+
+	#[test]
+	fn committed_claims_can_be_revealed() {
+		with_externalities(&mut new_test_ext(), || {
+			let sig = alice_sig(&42u64.encode());
+			assert_ok!(Claims::commit_claim(Origin::signed(42), claim_commitment(&sig, &[7; 32])));
+			assert_noop!(
+				Claims::reveal_claim(Origin::signed(42), sig, [8; 32]),
+				"Claim doesn't match its commitment"
+			);
+			assert_ok!(Claims::reveal_claim(Origin::signed(42), sig, [7; 32]));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Claims::commitment(&42), None);
+		});
+	}
+
+	#[test]
+	fn revealing_without_commitment_doesnt_work() {
+		with_externalities(&mut new_test_ext(), || {
+			// Another account's commitment is of no use.
+			let sig = alice_sig(&42u64.encode());
+			assert_ok!(Claims::commit_claim(Origin::signed(69), claim_commitment(&sig, &[7; 32])));
+			assert_noop!(
+				Claims::reveal_claim(Origin::signed(42), sig, [7; 32]),
+				"No claim has been committed to"
+			);
+		});
+	}
+
+	#[test]
+	fn claim_multiple_skips_duplicate_signatures() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim_multiple(Origin::signed(42), vec![
+				alice_sig(&42u64.encode()),
+				bob_sig(&42u64.encode()),
+				alice_sig(&42u64.encode()),
+			]));
+			assert_eq!(claims_events(), vec![
+				RawEvent::TotalUpdated(200),
+				RawEvent::TotalUpdated(0),
+				RawEvent::AllClaimsCompleted,
+				RawEvent::BatchClaimed(vec![(42, alice_eth(), 100), (42, bob_eth(), 200)]),
+			]);
+			assert_eq!(Balances::free_balance(&42), 300);
+			assert_eq!(Claims::total(), 0);
+		});
+	}
+
+	#[test]
+	fn addresses_derived_from_one_seed_can_be_claimed_together() {
+		// Stand-ins for the keys an HD wallet derives from its seed, one per index.
+		let derived = |i: u8|
+			secp256k1::SecretKey::parse(&keccak256(&[&b"seed/"[..], &[i][..]].concat())).unwrap();
+		let eth = |secret: &secp256k1::SecretKey| {
+			let public = secp256k1::PublicKey::from_secret_key(secret);
+			let mut res = EthereumAddress::default();
+			res.copy_from_slice(&keccak256(&public.serialize()[1..65])[12..]);
+			res
+		};
+		let keys: Vec<_> = (0..5).map(derived).collect();
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: keys.iter().enumerate().map(|(i, k)| (eth(k), 10 * (i as u64 + 1))).collect(),
+			..Default::default()
+		}), || {
+			let sigs = keys.iter().map(|k| sign_message(k, &create_msg(&42u64.encode()))).collect();
+			assert_ok!(Claims::claim_multiple(Origin::signed(42), sigs));
+			assert_eq!(Balances::free_balance(&42), 150);
+			assert_eq!(Claims::total(), 0);
+			assert!(keys.iter().all(|k| Claims::claims(&eth(k)).is_none()));
+
+			let sigs = vec![alice_sig(&42u64.encode()); MAX_SIGNATURES_PER_EXTRINSIC + 1];
+			assert_noop!(
+				Claims::claim_multiple(Origin::signed(42), sigs),
+				"Too many signatures in one extrinsic"
+			);
+		});
+	}
+
+	#[test]
+	fn batches_over_the_signature_limit_dont_work() {
+		with_externalities(&mut new_test_ext(), || {
+			let claims = vec![(42, alice_sig(&42u64.encode())); MAX_SIGNATURES_PER_EXTRINSIC + 1];
+			assert_noop!(
+				Claims::claim_batch(Origin::signed(1), claims.clone(), false),
+				"Too many signatures in one extrinsic"
+			);
+			assert_noop!(
+				Claims::dispatch(Call::migrate_claims(claims), Origin::INHERENT),
+				"Too many signatures in one extrinsic"
+			);
+		});
+	}
+
+	#[test]
+	fn estimate_fee_works() {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test>{
+			transaction_base_fee: 10,
+			transaction_byte_fee: 2,
+			..Default::default()
+		}.build_storage().unwrap().0);
+		with_externalities(&mut t.into(), || {
+			let len = Call::<Test>::claim(alice_sig(&42u64.encode())).encode().len();
+			assert_eq!(Claims::estimate_fee(len, false), 10 + 2 * len as u64);
+			assert_eq!(Claims::estimate_fee(len, true), 0);
+		});
+	}
+
+	#[test]
+	fn batch_claims_can_be_aggregated() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200), (charlie_eth(), 300)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim_batch(Origin::signed(1), vec![
+				(69, bob_sig(&69u64.encode())),
+				(7, charlie_sig(&7u64.encode())),
+				(42, alice_sig(&42u64.encode())),
+			], true));
+			assert_eq!(claims_events(), vec![
+				RawEvent::TotalUpdated(400),
+				RawEvent::TotalUpdated(100),
+				RawEvent::TotalUpdated(0),
+				RawEvent::AllClaimsCompleted,
+				RawEvent::BatchClaimed(vec![
+					(69, bob_eth(), 200),
+					(7, charlie_eth(), 300),
+					(42, alice_eth(), 100),
+				]),
+			]);
+			assert_eq!(Balances::free_balance(&7), 300);
+		});
+	}
+
+	#[test]
+	fn claiming_to_module_account_doesnt_work() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::set_module_account(Some(42)));
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Claims may not be made to the module account"
+			);
+			assert_ok!(Claims::claim(Origin::signed(43), alice_sig(&43u64.encode())));
+		});
+	}
+
+	#[test]
+	fn setting_prefix_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(Claims::message_prefix(), CLAIM_MESSAGE_PREFIX.to_vec());
+			assert_ok!(Claims::set_prefix(b"Pay KSMs to the Kusama account:".to_vec()));
+			assert_eq!(Claims::message_prefix(), b"Pay KSMs to the Kusama account:".to_vec());
+
+			// Signatures over the old prefix no longer recover the claimant.
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+			let msg = frame_msg(
+				b"Pay KSMs to the Kusama account:", &PayloadEncoding::Raw, &42u64.encode(), &[]
+			);
+			assert_eq!(Claims::claim_message(&42u64.encode()), msg);
+			assert_ok!(Claims::claim(Origin::signed(42), sign_message(&alice_secret(), &msg)));
+			assert_eq!(Balances::free_balance(&42), 100);
+
+			assert_ok!(Claims::set_prefix(vec![]));
+			assert_eq!(Claims::message_prefix(), CLAIM_MESSAGE_PREFIX.to_vec());
+		});
+	}
+
+	#[test]
+	fn claiming_with_statement_works() {
+		let statement = b"I am not a US person".to_vec();
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			statement: statement.clone(),
+			..Default::default()
+		}), || {
+			let msg = Claims::claim_message(&42u64.encode());
+			assert_eq!(msg, create_msg_with(&TEMPLATES[0], &42u64.encode(), &statement));
+			assert!(msg.ends_with(&statement));
+
+			// A signature without the statement, or with another one, recovers another address.
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+			let other = create_msg_with(&TEMPLATES[0], &42u64.encode(), b"I am a US person");
+			assert_noop!(
+				Claims::claim(Origin::signed(42), sign_message(&alice_secret(), &other)),
+				"Ethereum address has no claim"
+			);
+
+			assert_ok!(Claims::claim(Origin::signed(42), sign_message(&alice_secret(), &msg)));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn too_many_failed_attempts_are_throttled() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			max_failed_attempts: 2,
+			failed_attempts_window: 10,
+			..Default::default()
+		}), || {
+			System::set_block_number(1);
+			for _ in 0..2 {
+				assert_eq!(
+					Claims::claim(Origin::signed(42), bob_sig(&42u64.encode())),
+					Err("Ethereum address has no claim")
+				);
+			}
+			assert_eq!(Claims::failed_attempts(&42), (2, 1));
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Too many failed claim attempts"
+			);
+			// Other accounts aren't affected.
+			assert_ok!(Claims::claim_via_lookup(Origin::signed(43), TestAddress::Id(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn throttling_ends_with_the_window() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			max_failed_attempts: 1,
+			failed_attempts_window: 10,
+			..Default::default()
+		}), || {
+			System::set_block_number(1);
+			assert!(Claims::claim(Origin::signed(42), bob_sig(&42u64.encode())).is_err());
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Too many failed claim attempts"
+			);
+
+			System::set_block_number(11);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn failed_attempts_of_every_claim_call_are_throttled() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			max_failed_attempts: 3,
+			failed_attempts_window: 10,
+			..Default::default()
+		}), || {
+			System::set_block_number(1);
+			// Bob has no claim, so each of these is a failed attempt of the sender.
+			assert!(Claims::claim_to(Origin::signed(42), 69, bob_sig(&69u64.encode())).is_err());
+			assert!(Claims::claim_with_fee(Origin::signed(42), 69, bob_sig(&(69u64, 1u64).encode()), 1).is_err());
+			assert!(Claims::claim_batch(Origin::signed(42), vec![(69, bob_sig(&69u64.encode()))], false).is_err());
+			assert_eq!(Claims::failed_attempts(&42), (3, 1));
+
+			let sig = alice_sig(&42u64.encode());
+			assert_noop!(Claims::claim_checked(Origin::signed(42), alice_eth(), sig), "Too many failed claim attempts");
+			assert_noop!(Claims::claim_to(Origin::signed(42), 42, sig), "Too many failed claim attempts");
+			assert_noop!(
+				Claims::claim_via_lookup(Origin::signed(42), TestAddress::Id(42), sig),
+				"Too many failed claim attempts"
+			);
+			assert_noop!(Claims::claim_with_template(Origin::signed(42), sig, 0), "Too many failed claim attempts");
+			assert_noop!(Claims::claim_auto(Origin::signed(42), sig), "Too many failed claim attempts");
+			assert_noop!(
+				Claims::claim_with_fee(Origin::signed(42), 42, alice_sig(&(42u64, 1u64).encode()), 1),
+				"Too many failed claim attempts"
+			);
+			assert_noop!(
+				Claims::claim_batch(Origin::signed(42), vec![(42, sig)], false),
+				"Too many failed claim attempts"
+			);
+
+			// Only the sender is throttled, not the claim.
+			assert_ok!(Claims::claim_to(Origin::signed(43), 42, sig));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn claiming_checked_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(
+				Claims::claim_checked(Origin::signed(42), bob_eth(), alice_sig(&42u64.encode())),
+				"Signature recovers to another Ethereum address"
+			);
+			assert_ok!(Claims::claim_checked(Origin::signed(42), alice_eth(), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn claiming_with_expiry_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let sig = alice_sig(&(42u64, 10u64).encode());
+			System::set_block_number(11);
+			assert_noop!(
+				Claims::claim_with_expiry(Origin::signed(42), sig, 10),
+				"Claim signature has expired"
+			);
+
+			// The bound is part of the message, so it can't be extended.
+			assert_noop!(
+				Claims::claim_with_expiry(Origin::signed(42), sig, 11),
+				"Ethereum address has no claim"
+			);
+
+			System::set_block_number(10);
+			assert_ok!(Claims::claim_with_expiry(Origin::signed(42), sig, 10));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn block_bound_claims_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			block_bound_tolerance: 5,
+			..Default::default()
+		}), || {
+			let sig = alice_sig(&(42u64, 10u64).encode());
+			System::set_block_number(9);
+			assert_noop!(
+				Claims::claim_block_bound(Origin::signed(42), sig, 10),
+				"Claim is bound to a future block"
+			);
+			System::set_block_number(16);
+			assert_noop!(
+				Claims::claim_block_bound(Origin::signed(42), sig, 10),
+				"Claim is bound to a block too long ago"
+			);
+
+			// The block is part of the message, so it can't be moved up.
+			assert_noop!(
+				Claims::claim_block_bound(Origin::signed(42), sig, 16),
+				"Ethereum address has no claim"
+			);
+
+			System::set_block_number(15);
+			assert_ok!(Claims::claim_block_bound(Origin::signed(42), sig, 10));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn para_bound_claims_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			local_para_id: 100,
+			..Default::default()
+		}), || {
+			let sig = alice_sig(&(42u64, 200u32).encode());
+			assert_noop!(
+				Claims::claim_para_bound(Origin::signed(42), sig, 200),
+				"Claim is bound to another parachain"
+			);
+			// The para-id is part of the message, so it can't be swapped for the local one.
+			assert_noop!(
+				Claims::claim_para_bound(Origin::signed(42), sig, 100),
+				"Ethereum address has no claim"
+			);
+
+			let sig = alice_sig(&(42u64, 100u32).encode());
+			assert_ok!(Claims::claim_para_bound(Origin::signed(42), sig, 100));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn claims_can_be_made_to_a_multisig_account() {
+		with_externalities(&mut new_test_ext(), || {
+			// The account of a multisig of the accounts 1, 2 and 3, which none of them holds the
+			// key of.
+			let multisig = 1_000u64;
+			assert_noop!(
+				Claims::claim_to(Origin::signed(1), multisig, alice_sig(&1u64.encode())),
+				"Ethereum address has no claim"
+			);
+
+			assert_ok!(Claims::claim_to(Origin::signed(1), multisig, alice_sig(&multisig.encode())));
+			assert_eq!(Balances::free_balance(&multisig), 100);
+			assert_eq!(Balances::free_balance(&1), 0);
+			assert_eq!(
+				claims_events().last(),
+				Some(&RawEvent::Claimed(multisig, alice_eth(), 100, 100, 0, true))
+			);
+		});
+	}
+
+	#[test]
+	fn claiming_with_identity_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let sig = alice_sig(&(42u64, b"Alice".to_vec()).encode());
+			// The name is part of the message, so it can't be swapped out.
+			assert_noop!(
+				Claims::claim_with_identity(Origin::signed(42), sig, b"Mallory".to_vec()),
+				"Ethereum address has no claim"
+			);
+			let empty = alice_sig(&(42u64, Vec::<u8>::new()).encode());
+			assert_noop!(
+				Claims::claim_with_identity(Origin::signed(42), empty, vec![]),
+				"Invalid display name"
+			);
+
+			assert_ok!(Claims::claim_with_identity(Origin::signed(42), sig, b"Alice".to_vec()));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(DISPLAY_NAMES.with(|n| n.borrow().clone()), vec![(42, b"Alice".to_vec())]);
+		});
+	}
+
+	#[test]
+	fn claiming_to_a_location_teleports_the_claim() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			vesting: vec![(alice_eth(), (40, 10))],
+			..Default::default()
+		}), || {
+			let dest = MultiLocation::ParachainAccount(100, vec![1; 32]);
+			let sig = alice_sig(&(42u64, dest.clone()).encode());
+			// The location is part of the message, so it can't be swapped out.
+			let other = MultiLocation::ParachainAccount(100, vec![2; 32]);
+			assert_noop!(
+				Claims::claim_to_location(Origin::signed(42), sig, other),
+				"Ethereum address has no claim"
+			);
+			let unknown = MultiLocation::ParachainAccount(200, vec![1; 32]);
+			assert_noop!(
+				Claims::claim_to_location(Origin::signed(42), alice_sig(&(42u64, unknown.clone()).encode()), unknown),
+				"Unknown parachain"
+			);
+
+			assert_ok!(Claims::claim_to_location(Origin::signed(42), sig, dest.clone()));
+			assert_eq!(TELEPORTS.with(|t| t.borrow().clone()), vec![(dest.clone(), 60)]);
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_eq!(Claims::vested(&42), vec![(40, 10)]);
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(claims_events().last(), Some(&RawEvent::ClaimTeleported(42, alice_eth(), dest, 60)));
+		});
+	}
+
+	#[test]
+	fn webauthn_claims_work() {
+		assert_eq!(to_base64url(b"Man"), b"TWFu".to_vec());
+		assert_eq!(to_base64url(b"Ma"), b"TWE".to_vec());
+		assert_eq!(to_base64url(&[0xfb, 0xff]), b"-_8".to_vec());
+
+		let public_key = H512::from([7u8; 64]);
+		let passkey_eth = TestDerivation::derive_address(&public_key.0);
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(passkey_eth, 100)],
+			..Default::default()
+		}), || {
+			let client_data = |kind: &[u8], who: u64| [
+				&b"{\"type\":\""[..], kind, &b"\",\"challenge\":\""[..],
+				&to_base64url(&keccak256(&Claims::claim_message(&who.encode())))[..],
+				&b"\",\"origin\":\"https://claims.polkadot.network\"}"[..],
+			].concat();
+			let auth_data = vec![0u8; 37];
+			let assertion = |client_data: &[u8]| {
+				let hash = keccak256(&[&public_key.0[..], &auth_data[..], client_data].concat());
+				let mut signature = [0u8; 64];
+				signature[..32].copy_from_slice(&hash);
+				signature[32..].copy_from_slice(&hash);
+				H512::from(signature)
+			};
+
+			let registration = client_data(b"webauthn.create", 42);
+			assert_noop!(
+				Claims::claim_webauthn(
+					Origin::signed(42), public_key, auth_data.clone(), registration.clone(), assertion(&registration)
+				),
+				"WebAuthn client data is not of an assertion"
+			);
+			let for_other = client_data(b"webauthn.get", 43);
+			assert_noop!(
+				Claims::claim_webauthn(
+					Origin::signed(42), public_key, auth_data.clone(), for_other.clone(), assertion(&for_other)
+				),
+				"WebAuthn challenge is not the claim message"
+			);
+			let client_data = client_data(b"webauthn.get", 42);
+			assert_noop!(
+				Claims::claim_webauthn(
+					Origin::signed(42), public_key, auth_data.clone(), client_data.clone(), H512::default()
+				),
+				"Invalid WebAuthn assertion"
+			);
+
+			let signature = assertion(&client_data);
+			assert_ok!(
+				Claims::claim_webauthn(Origin::signed(42), public_key, auth_data.clone(), client_data, signature)
+			);
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Claims::claims(&passkey_eth), None);
+		});
+	}
+
+	#[test]
+	fn prehashed_claims_work() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(
+				Claims::claim_prehashed(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+			let sig = alice_sig(&sr_io::blake2_256(&42u64.encode()));
+			assert_ok!(Claims::claim_prehashed(Origin::signed(42), sig));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn claiming_with_referrer_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			referral_rate: Perbill::from_billionths(100_000_000),
+			..Default::default()
+		}), || {
+			// The referrer is bound by the signature.
+			assert_noop!(
+				Claims::claim_with_referrer(Origin::signed(42), Some(8), alice_sig(&(42u64, 7u64).encode())),
+				"Ethereum address has no claim"
+			);
+			assert_ok!(Claims::claim_with_referrer(Origin::signed(42), Some(7), alice_sig(&(42u64, 7u64).encode())));
+			assert_eq!(Balances::free_balance(&42), 90);
+			assert_eq!(Balances::free_balance(&7), 10);
+			assert!(claims_events().contains(&RawEvent::ReferrerPaid(7, alice_eth(), 10)));
+
+			// Without a referrer, it's a normal claim.
+			assert_ok!(Claims::claim_with_referrer(Origin::signed(69), None, bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 200);
+		});
+	}
+
+	#[test]
+	fn claim_shares_convert_to_amounts() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			claim_shares: vec![
+				(bob_eth(), Perbill::from_billionths(5_000_000)),
+				(charlie_eth(), Perbill::from_billionths(250_000_000)),
+			],
+			share_pool: 10_000,
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::claims(&bob_eth()), Some(50));
+			assert_eq!(Claims::claims(&charlie_eth()), Some(2500));
+			assert_eq!(Claims::total(), 2650);
+			assert_eq!(Claims::addresses(), vec![alice_eth(), bob_eth(), charlie_eth()]);
+
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 50);
+		});
+	}
+
+	#[test]
+	fn shares_of_balances_wider_than_u64_are_not_truncated() {
+		let amount = u64::max_value() as u128 * 1_000;
+		assert_eq!(perbill_of(Perbill::from_billionths(500_000_000), amount), amount / 2);
+		assert_eq!(perbill_of(Perbill::from_billionths(1), 1_999_999_999u128), 1);
+		assert_eq!(perbill_of(Perbill::from_billionths(1_000_000_000), amount), amount);
+	}
+
+	#[test]
+	#[should_panic(expected = "Claim shares exceed the whole pool")]
+	fn claim_shares_over_the_whole_pool_dont_work() {
+		new_test_ext_with(GenesisConfig::<Test>{
+			claim_shares: vec![
+				(bob_eth(), Perbill::from_billionths(600_000_000)),
+				(charlie_eth(), Perbill::from_billionths(400_000_001)),
+			],
+			share_pool: 10_000,
+			..Default::default()
+		});
+	}
+
+	#[test]
+	fn genesis_claims_are_rounded() {
+		let config = |rounding| GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 1049), (bob_eth(), 1050), (charlie_eth(), 2000)],
+			rounding,
+			rounding_unit: 100,
+			..Default::default()
+		};
+		for &(rounding, amounts, residual) in &[
+			(Rounding::Floor, [1000, 1000, 2000], (99, 0)),
+			(Rounding::Ceil, [1100, 1100, 2000], (0, 101)),
+			(Rounding::Nearest, [1000, 1100, 2000], (49, 50)),
+		] {
+			with_externalities(&mut new_test_ext_with(config(rounding)), || {
+				assert_eq!(Claims::claims(&alice_eth()), Some(amounts[0]));
+				assert_eq!(Claims::claims(&bob_eth()), Some(amounts[1]));
+				assert_eq!(Claims::claims(&charlie_eth()), Some(amounts[2]));
+				assert_eq!(Claims::total(), amounts.iter().sum());
+				assert_eq!(Claims::rounding_residual(), residual);
+			});
+		}
+	}
+
+	#[test]
+	fn totals_by_category_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200), (charlie_eth(), 300)],
+			categories: vec![
+				(alice_eth(), b"team".to_vec()),
+				(bob_eth(), b"public".to_vec()),
+				(charlie_eth(), b"public".to_vec()),
+			],
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::total_by_category(b"team".to_vec()), 100);
+			assert_eq!(Claims::total_by_category(b"public".to_vec()), 500);
+
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Claims::total_by_category(b"team".to_vec()), 100);
+			assert_eq!(Claims::total_by_category(b"public".to_vec()), 300);
+			assert_eq!(Claims::claim_category(&bob_eth()), None);
+
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::total_by_category(b"team".to_vec()), 0);
+			assert_eq!(Claims::total_by_category(b"public".to_vec()), 300);
+			assert_eq!(Claims::total(), 300);
+		});
+	}
+
+	#[test]
+	fn empty_account_encodings_are_refused() {
+		// A unit struct stands in for an account type which encodes to no bytes.
+		#[derive(Encode)]
+		struct Nobody;
+		assert_eq!(encoded_account(&Nobody), Err("Account has an empty encoding"));
+		assert_eq!(encoded_account(&42u64), Ok(42u64.encode()));
+
+		// Every claim call recovers its signer through `recover_signer`, which the test
+		// runtime's `u64` accounts can't reach with an empty encoding, so it's called directly.
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(
+				Claims::recover_signer(&alice_sig(&[]), &Nobody, &[]),
+				Err("Account has an empty encoding")
+			);
+			assert_eq!(
+				Claims::recover_signer(&alice_sig(&[]), &Nobody, &69u64.encode()),
+				Err("Account has an empty encoding")
+			);
+			assert_eq!(Claims::recover_signer(&alice_sig(&42u64.encode()), &42u64, &[]), Ok(alice_eth()));
+		});
+	}
+
+	#[test]
+	fn verify_many_works() {
+		let results = verify_many(&[
+			(42u64.encode(), alice_sig(&42u64.encode())),
+			(69u64.encode(), ([0; 32], [0; 32], RecoveryId::default())),
+			(69u64.encode(), bob_sig(&69u64.encode())),
+			// Signed for another account, so another address is recovered.
+			(7u64.encode(), charlie_sig(&42u64.encode())),
+		]);
+		assert_eq!(&results[..3], &[Some(alice_eth()), None, Some(bob_eth())][..]);
+		assert!(results[3].is_some());
+		assert!(results[3] != Some(charlie_eth()));
+	}
+
+	#[test]
+	fn out_of_range_recovery_ids_dont_decode() {
+		for v in &[0u8, 1, 27, 28] {
+			assert_eq!(RecoveryId::decode(&mut &[*v][..]), RecoveryId::new(*v));
+			assert_eq!(RecoveryId::new(*v).unwrap().encode(), vec![*v]);
+		}
+		assert_eq!(RecoveryId::new(28).unwrap().index(), 1);
+		for v in &[2u8, 26, 29, 100, 251] {
+			assert_eq!(RecoveryId::new(*v), None);
+			assert_eq!(RecoveryId::decode(&mut &[*v][..]), None);
+		}
+
+		// -5 as an `i8`, at the end of an otherwise well-formed signature...
+		let mut sig = alice_sig(&42u64.encode()).encode();
+		sig[64] = 251;
+		assert_eq!(EcdsaSignature::decode(&mut &sig[..]), None);
+		// ...so a call carrying it doesn't decode either.
+		let mut call = Call::<Test>::claim(alice_sig(&42u64.encode())).encode();
+		let last = call.len() - 1;
+		call[last] = 100;
+		assert!(Call::<Test>::decode(&mut &call[..]).is_none());
+	}
+
+	#[test]
+	fn signature_from_hex_works() {
+		let hex = "7505f2880114da51b3f5d535f8687953c0ab9af4ab81e592eaebebf53b728d2b6dfd9b5bcd70fee412b1f31360e7c2774009305cb84fc50c1d0ff8034dfa5fff1c";
+		let sig = hex!["7505f2880114da51b3f5d535f8687953c0ab9af4ab81e592eaebebf53b728d2b6dfd9b5bcd70fee412b1f31360e7c2774009305cb84fc50c1d0ff8034dfa5fff1c"];
+		let sig = EcdsaSignature::decode(&mut &sig[..]).unwrap();
+		assert_eq!(signature_from_hex(hex), Ok(sig));
+		assert_eq!(signature_from_hex(&format!("0x{}", hex)), Ok(sig));
+		assert_eq!(signature_from_hex(&hex.to_uppercase()), Ok(sig));
+
+		assert_eq!(signature_from_hex(&hex[2..]), Err("Signature hex must be 65 bytes"));
+		assert_eq!(signature_from_hex(&format!("0x{}00", hex)), Err("Signature hex must be 65 bytes"));
+		assert_eq!(signature_from_hex(&format!("zz{}", &hex[2..])), Err("Invalid hex in signature"));
+	}
+
+	#[test]
+	fn checksum_string_works() {
+		assert_eq!(
+			to_checksum_string(&hex!["df67ec7eae23d2459694685257b6fc59d1baa1fe"]),
+			b"0xDF67EC7EAe23D2459694685257b6FC59d1BAA1FE".to_vec()
+		);
+		// test vector from EIP-55.
+		assert_eq!(
+			to_checksum_string(&hex!["5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"]),
+			b"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_vec()
+		);
+	}
+
+	#[test]
+	fn claiming_with_fee_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let sig = alice_sig(&(42u64, 10u64).encode());
+			assert_noop!(
+				Claims::claim_with_fee(Origin::signed(1), 42, sig, 20),
+				"Ethereum address has no claim"
+			);
+			assert_noop!(
+				Claims::claim_with_fee(Origin::signed(1), 42, alice_sig(&(42u64, 101u64).encode()), 101),
+				"Fee exceeds the claim"
+			);
+			assert_ok!(Claims::claim_with_fee(Origin::signed(1), 42, sig, 10));
+			assert_eq!(Balances::free_balance(&1), 10);
+			assert_eq!(Balances::free_balance(&42), 90);
+			assert_eq!(Claims::total(), 0);
+		});
+	}
+
+	#[test]
+	fn claiming_with_mock_currency_works() {
+		with_externalities(&mut new_mock_ext(vec![(alice_eth(), 100)]), || {
+			assert_ok!(MockClaims::claim(MockOrigin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(DEPOSITS.with(|d| d.borrow().clone()), vec![(42, 100)]);
+			assert_eq!(MockClaims::total(), 0);
+		});
+	}
+
+	#[test]
+	fn zero_balance_account_can_claim_paying_the_fee_out_of_the_claim() {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test>{
+			transaction_base_fee: 10,
+			transaction_byte_fee: 1,
+			balances: vec![(1, 50)],
+			..Default::default()
+		}.build_storage().unwrap().0);
+		t.extend(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			..Default::default()
+		}.build_storage().unwrap().0);
+		with_externalities(&mut t.into(), || {
+			type Payment = FeeFromClaim<Test, Balances>;
+			// An account with funds pays its fee as usual.
+			assert_ok!(Payment::make_payment(&1, 5));
+			assert_eq!(Balances::free_balance(&1), 35);
+			assert!(Claims::deferred_fees().is_empty());
+
+			// An account with none defers it, and pays it out of what its claim credits.
+			assert_ok!(Payment::make_payment(&42, 5));
+			assert_eq!(Claims::deferred_fees(), vec![(42, 15)]);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 85);
+			assert!(Claims::deferred_fees().is_empty());
+			assert_eq!(claims_events().last(), Some(&RawEvent::DeferredFeePaid(42, 15)));
+
+			// A fee which no claim pays is forgiven at the end of the block.
+			assert_ok!(Payment::make_payment(&43, 5));
+			assert_eq!(Claims::deferred_fees(), vec![(43, 15)]);
+			Claims::on_finalise(1);
+			assert!(Claims::deferred_fees().is_empty());
+			assert_eq!(Balances::free_balance(&43), 0);
+		});
+	}
+
+	#[test]
+	fn claiming_via_lookup_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(
+				Claims::claim_via_lookup(Origin::signed(1), TestAddress::Index(10), alice_sig(&42u64.encode())),
+				"Unknown account index"
+			);
+			assert_noop!(
+				Claims::claim_via_lookup(Origin::signed(1), TestAddress::Index(3), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+			assert_ok!(Claims::claim_via_lookup(Origin::signed(1), TestAddress::Index(2), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Balances::free_balance(&1), 0);
+		});
+	}
+
+	#[test]
+	fn claiming_with_compact_signature_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let (r, mut s, v) = alice_sig(&42u64.encode());
+			s[0] |= v.index() << 7;
+			let mut compact = H512::default();
+			compact[..32].copy_from_slice(&r);
+			compact[32..].copy_from_slice(&s);
+
+			assert_eq!(from_compact(&compact), alice_sig(&42u64.encode()));
+			assert_ok!(Claims::claim_compact(Origin::signed(42), compact));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn claiming_before_claims_start_doesnt_work() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			claims_start: 5,
+			..Default::default()
+		}), || {
+			System::set_block_number(4);
+			Claims::on_initialise(4);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Claims are not yet open"
+			);
+			assert!(!claims_events().contains(&RawEvent::ClaimsOpened));
+
+			System::set_block_number(5);
+			Claims::on_initialise(5);
+			assert!(claims_events().contains(&RawEvent::ClaimsOpened));
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
+
+	#[test]
+	fn expired_claims_are_swept_over_bounded_calls() {
+		// 100 small claims, of 199 in all, and Alice's.
+		let mut claims: Vec<_> = (0..100u8).map(|i| ([i; 20], 1u64 + i as u64 % 3)).collect();
+		claims.push((alice_eth(), 100));
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims,
+			claims_end: 10,
+			..Default::default()
+		}), || {
+			assert_noop!(Claims::sweep_expired(30), "Claims have not yet expired");
+
+			System::set_block_number(10);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Claims have closed"
+			);
+
+			let mut calls = 0;
+			while Claims::total() != 0 {
+				assert_ok!(Claims::sweep_expired(30));
+				calls += 1;
+				assert!(calls <= 4);
+			}
+			assert_eq!(calls, 4);
+			assert_eq!(Claims::sweep_cursor(), 101);
+			assert!(Claims::export_claims().is_empty());
+			// The first 30 claims come to 90.
+			assert!(claims_events().contains(&RawEvent::TotalChanged(299, 209, TotalChange::Expired)));
+
+			// Once everything is swept, further calls sweep nothing.
+			assert_ok!(Claims::sweep_expired(30));
+			assert_eq!(claims_events().last(), Some(&RawEvent::ClaimsExpired(vec![])));
+		});
+	}
+
+	#[test]
+	fn expired_chunked_and_labeled_claims_are_swept() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			chunked_claims: vec![(bob_eth(), vec![100, 100, 50])],
+			labeled_claims: vec![(charlie_eth(), vec![(b"team".to_vec(), 100), (b"bonus".to_vec(), 10)])],
+			top_up_pot: 5,
+			claims_end: 10,
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_ok!(Claims::claim_label(Origin::signed(7), charlie_sig(&7u64.encode()), b"team".to_vec()));
+			assert_eq!(Claims::total(), 260);
+
+			System::set_block_number(10);
+			assert_ok!(Claims::sweep_expired(3));
+			assert_eq!(
+				claims_events().last(),
+				Some(&RawEvent::ClaimsExpired(vec![(alice_eth(), 100), (bob_eth(), 150), (charlie_eth(), 10)]))
+			);
+			assert_eq!(Claims::sweep_cursor(), Claims::address_count());
+			assert!(Claims::chunked_claims(&bob_eth()).is_empty());
+			assert_eq!(Claims::chunks_claimed(&bob_eth()), 0);
+			assert!(Claims::labeled_claims(&charlie_eth()).is_empty());
+			// The top-up pot isn't in `Total`, so nothing is left.
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(Claims::top_up_pot(), 5);
+		});
+	}
+
+	#[test]
+	fn claims_storage_is_killed_with_funds_left_in_the_top_up_pot() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			top_up_pot: 50,
+			claims_end: 10,
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::total(), 300);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::total(), 200);
+			assert!(!claims_events().contains(&RawEvent::AllClaimsCompleted));
+
+			System::set_block_number(10);
+			assert_noop!(Claims::kill_claims_storage(10), "Claims are still outstanding");
+			assert_ok!(Claims::sweep_expired(10));
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(Claims::top_up_pot(), 50);
+
+			assert_ok!(Claims::kill_claims_storage(10));
+			assert!(Claims::addresses().is_empty());
+			assert_eq!(Claims::claims(&alice_eth()), None);
+			assert_eq!(Claims::claims(&bob_eth()), None);
+		});
+	}
+
+	#[test]
+	fn all_claims_are_completed_with_funds_left_in_the_top_up_pot() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			top_up_pot: 50,
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(Claims::top_up_pot(), 50);
+			assert!(claims_events().contains(&RawEvent::AllClaimsCompleted));
+		});
+	}
+
+	#[test]
+	fn expired_child_claims_are_swept_or_dropped() {
+		let ext = || new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			claims_end: 10,
+			..Default::default()
+		});
+
+		with_externalities(&mut ext(), || {
+			assert_ok!(Claims::import_child_claims(vec![(bob_eth(), 200), (charlie_eth(), 300)]));
+			assert_eq!(Claims::addresses(), vec![alice_eth(), bob_eth(), charlie_eth()]);
+
+			System::set_block_number(10);
+			assert_ok!(Claims::sweep_expired(2));
+			assert_eq!(Claims::child_claim(&bob_eth()), None);
+			assert_eq!(Claims::child_claim(&charlie_eth()), Some(300));
+			assert_eq!(Claims::total(), 300);
+			assert_ok!(Claims::sweep_expired(2));
+			assert_eq!(Claims::child_claim(&charlie_eth()), None);
+			assert_eq!(Claims::total(), 0);
+		});
+
+		with_externalities(&mut ext(), || {
+			assert_ok!(Claims::import_child_claims(vec![(bob_eth(), 200), (charlie_eth(), 300)]));
+			assert_noop!(Claims::kill_child_claims(3), "Claims have not yet expired");
+
+			System::set_block_number(10);
+			// Alice's claim isn't in the child trie, so only Bob's is dropped.
+			assert_ok!(Claims::kill_child_claims(2));
+			assert_eq!(Claims::child_kill_cursor(), 2);
+			assert_eq!(Claims::child_claim(&bob_eth()), None);
+			assert_eq!(Claims::child_claim(&charlie_eth()), Some(300));
+			assert_eq!(Claims::total(), 400);
+			assert_eq!(claims_events().last(), Some(&RawEvent::ClaimsExpired(vec![(bob_eth(), 200)])));
+
+			assert_ok!(Claims::kill_child_claims(2));
+			assert_eq!(Claims::child_kill_cursor(), 0);
+			assert_eq!(Claims::child_claim(&charlie_eth()), None);
+			assert_eq!(Claims::claims(&alice_eth()), Some(100));
+			assert_eq!(Claims::total(), 100);
+			assert_eq!(claims_events().last(), Some(&RawEvent::ClaimsExpired(vec![(charlie_eth(), 300)])));
+
+			// Sweeping after the child claims were dropped doesn't take them again.
+			assert_ok!(Claims::sweep_expired(3));
+			assert_eq!(Claims::total(), 0);
+		});
+	}
+
+	#[test]
+	fn claims_before_claims_start_fail_ahead_of_recovery() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			claims_start: 5,
+			..Default::default()
+		}), || {
+			// The signature is never looked at, so even one which can't be recovered fails on
+			// the cheaper check.
+			let sig = ([0; 32], [0; 32], RecoveryId::default());
+			assert_noop!(Claims::claim(Origin::signed(42), sig), "Claims are not yet open");
+			assert_noop!(Claims::claim_batch(Origin::signed(42), vec![(42, sig)], false), "Claims are not yet open");
+			assert_noop!(Claims::migrate_claims(Origin::INHERENT, vec![(42, sig)]), "Claims are not yet open");
+			assert_noop!(Claims::claim_multiple(Origin::signed(42), vec![sig]), "Claims are not yet open");
+			assert_noop!(Claims::claim_next_chunk(Origin::signed(42), sig), "Claims are not yet open");
+			assert_noop!(Claims::claim_label(Origin::signed(42), sig, b"team".to_vec()), "Claims are not yet open");
+			assert_noop!(Claims::claim_auto(Origin::signed(42), sig), "Claims are not yet open");
+			assert_noop!(
+				Claims::claim_ss58_digest(Origin::signed(42), b"BTRjp7Wgjtph9sH".to_vec(), sig),
+				"Claims are not yet open"
+			);
+			System::set_block_number(5);
+			assert_noop!(Claims::claim(Origin::signed(42), sig), "Invalid Ethereum signature");
+			assert_noop!(Claims::claim_next_chunk(Origin::signed(42), sig), "Invalid Ethereum signature");
+		});
+	}
+
+	#[test]
+	fn claims_start_can_be_set() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(Claims::set_claims_start(3));
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Claims are not yet open"
+			);
+			System::set_block_number(3);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+		});
+	}
 
-/// An event in this module.
-decl_event!(
-	pub enum Event<T> where
-		B = <T as balances::Trait>::Balance,
-		A = <T as system::Trait>::AccountId
-	{
-		/// Someone claimed some DOTs.
-		Claimed(A, EthereumAddress, B),
+	#[test]
+	fn second_claim_in_the_same_block_doesnt_work() {
+		with_externalities(&mut new_test_ext(), || {
+			// Two relayers submit the same claim, and both land in block 1.
+			System::set_block_number(1);
+			assert_ok!(Claims::claim_via_lookup(Origin::signed(1), TestAddress::Id(42), alice_sig(&42u64.encode())));
+			assert_noop!(
+				Claims::claim_via_lookup(Origin::signed(2), TestAddress::Id(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(
+				claims_events().into_iter().filter(|e| match e { RawEvent::Claimed(..) => true, _ => false }).count(),
+				1
+			);
+		});
 	}
-);
 
-decl_storage! {
-	// A macro for the Storage trait, and its implementation, for this module.
-	// This allows for type-safe usage of the Substrate storage database, so you can
-	// keep things around between blocks.
-	trait Store for Module<T: Trait> as Claims {
-		Claims get(claims) build(|config: &GenesisConfig<T>| {
-			config.claims.iter().map(|(a, b)| (a.clone(), b.clone())).collect::<Vec<_>>()
-		}): map EthereumAddress => Option<T::Balance>;
-		Total get(total) build(|config: &GenesisConfig<T>| {
-			config.claims.iter().fold(Zero::zero(), |acc: T::Balance, &(_, n)| acc + n)
-		}): T::Balance;
+	#[test]
+	fn history_is_kept() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			keep_history: true,
+			..Default::default()
+		}), || {
+			System::set_block_number(5);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::history(&alice_eth()), Some((42, 100, 5)));
+			assert_eq!(Claims::history(&bob_eth()), None);
+		});
 	}
-	add_extra_genesis {
-		config(claims): Vec<(EthereumAddress, T::Balance)>;
+
+	#[test]
+	fn history_is_not_kept_by_default() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::history(&alice_eth()), None);
+		});
 	}
-}
 
-fn ecdsa_recover(sig: &EcdsaSignature, msg: &[u8; 32]) -> Option<[u8; 64]> {
-	let v = secp256k1::RecoveryId::parse(if sig.2 > 26 { sig.2 - 27 } else { sig.2 } as u8).ok()?;
-	let rs = (sig.0, sig.1).using_encoded(secp256k1::Signature::parse_slice).ok()?;
-	let pubkey = secp256k1::recover(&secp256k1::Message::parse(msg), &rs, &v).ok()?;
-	let mut res = [0u8; 64];
-	res.copy_from_slice(&pubkey.serialize()[1..65]);
-	Some(res)
-}
+	#[test]
+	fn claiming_with_template_works() {
+		for template_id in 0..TEMPLATES.len() as u8 {
+			with_externalities(&mut new_test_ext(), || {
+				let msg = create_msg_with(&TEMPLATES[template_id as usize], &42u64.encode(), &[]);
+				let sig = sign_message(&alice_secret(), &msg);
+				assert_ok!(Claims::claim_with_template(Origin::signed(42), sig, template_id));
+				assert_eq!(Balances::free_balance(&42), 100);
+			});
+		}
+	}
 
-fn create_msg(who: &[u8]) -> Vec<u8> {
-	let prefix = b"Pay DOTs to the Polkadot account:";
-	let mut l = prefix.len() + who.len();
-	let mut rev = Vec::new();
-	while l > 0 {
-		rev.push(b'0' + (l % 10) as u8);
-		l /= 10;
-	}
-	let mut v = b"\x19Ethereum Signed Message:\n".to_vec();
-	v.extend(rev.into_iter().rev());
-	v.extend_from_slice(&prefix[..]);
-	v.extend_from_slice(who);
-	v
-}
+	#[test]
+	fn claiming_with_any_template_works() {
+		for template in &TEMPLATES {
+			with_externalities(&mut new_test_ext(), || {
+				let msg = create_msg_with(template, &42u64.encode(), &[]);
+				assert_ok!(Claims::claim_auto(Origin::signed(42), sign_message(&alice_secret(), &msg)));
+				assert_eq!(Balances::free_balance(&42), 100);
+			});
+		}
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(
+				Claims::claim_auto(Origin::signed(42), bob_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+		});
+	}
 
-fn eth_recover(s: &EcdsaSignature, who: &[u8]) -> Option<EthereumAddress> {
-	let msg = keccak256(&create_msg(who));
-	let mut res = EthereumAddress::default();
-	res.copy_from_slice(&keccak256(&ecdsa_recover(s, &msg)?[..])[12..]);
-	Some(res)
-}
+	#[test]
+	fn template_messages_are_framed_as_expected() {
+		let who = [0xab, 0x01];
+		assert_eq!(create_msg_with(&TEMPLATES[0], &who, &[]), create_msg(&who));
+		assert_eq!(
+			create_msg_with(&TEMPLATES[1], &who, &[]),
+			b"\x19Ethereum Signed Message:\n37Pay DOTs to the Polkadot account:ab01".to_vec()
+		);
+		assert_eq!(
+			create_msg_with(&TEMPLATES[2], &who, &[]),
+			b"\x19Ethereum Signed Message:\n39Pay DOTs to the Polkadot account:0xab01".to_vec()
+		);
+		assert_eq!(
+			create_msg_with(&TEMPLATES[3], &who, &[]),
+			b"\x19Ethereum Signed Message:\n40Pay DOTs to the Polkadot account: 0xab01".to_vec()
+		);
+	}
 
-decl_module! {
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-		/// Deposit one of this module's events by using the default implementation.
-		fn deposit_event<T>() = default;
+	#[test]
+	fn templates_use_their_own_prefix() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::set_prefix(b"Pay KSMs to the Kusama account:".to_vec()));
+			let who = 42u64.encode();
+			assert_eq!(
+				Claims::template_message(&TEMPLATES[2], &who),
+				frame_msg(b"Pay KSMs to the Kusama account:", &PayloadEncoding::PrefixedHex, &who, &[])
+			);
+			assert_eq!(Claims::template_message(&TEMPLATES[3], &who), create_msg_with(&TEMPLATES[3], &who, &[]));
 
-		/// Make a claim.
-		fn claim(origin, ethereum_signature: EcdsaSignature) {
-			// This is a public call, so we ensure that the origin is some signed account.
-			let sender = ensure_signed(origin)?;
-			
-			let signer = sender.using_encoded(|data|
-					eth_recover(&ethereum_signature, data)
-				).ok_or("Invalid Ethereum signature")?;
-			
-			let balance_due = <Claims<T>>::take(&signer)
-				.ok_or("Ethereum address has no claim")?;
-			
-			<Total<T>>::mutate(|t| if *t < balance_due {
-				panic!("Logic error: Pot less than the total of claims!")
-			} else {
-				*t -= balance_due
-			});
+			// Templates 2 and 3 only differ by their prefix, so recover different signers.
+			let sig = sign_message(&alice_secret(), &create_msg_with(&TEMPLATES[3], &who, &[]));
+			let recover = |template: &MessageTemplate| eth_recover_message::<TestDerivation>(
+				&sig, &Claims::template_message(template, &who)
+			);
+			assert_eq!(recover(&TEMPLATES[3]), Some(alice_eth()));
+			assert!(recover(&TEMPLATES[2]).is_some());
+			assert_ne!(recover(&TEMPLATES[2]), recover(&TEMPLATES[3]));
 
-			<balances::Module<T>>::increase_free_balance_creating(&sender, balance_due);
+			assert_noop!(
+				Claims::claim_with_template(Origin::signed(42), sig, 2),
+				"Ethereum address has no claim"
+			);
+			assert_ok!(Claims::claim_with_template(Origin::signed(42), sig, 3));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
 
-			// Let's deposit an event to let the outside world know this happened.
-			Self::deposit_event(RawEvent::Claimed(sender, signer, balance_due));
-		}
+	#[test]
+	fn claiming_with_wrong_template_doesnt_work() {
+		with_externalities(&mut new_test_ext(), || {
+			let msg = create_msg_with(&TEMPLATES[1], &42u64.encode(), &[]);
+			let sig = sign_message(&alice_secret(), &msg);
+			assert_noop!(
+				Claims::claim_with_template(Origin::signed(42), sig, 2),
+				"Ethereum address has no claim"
+			);
+			assert_noop!(
+				Claims::claim_with_template(Origin::signed(42), sig, TEMPLATES.len() as u8),
+				"Unknown message template"
+			);
+		});
 	}
-}
 
-#[cfg(test)]
-mod tests {
-	use secp256k1;
-	use tiny_keccak::keccak256;
-	use super::*;
+	#[test]
+	fn claimed_bits_work() {
+		with_externalities(&mut new_test_ext(), || {
+			assert!(!Claims::is_index_claimed(0));
+			assert!(!Claims::is_index_claimed(1000));
 
-	use sr_io::{self as runtime_io, with_externalities};
-	use substrate_primitives::{H256, Blake2Hasher, hexdisplay::HexDisplay};
-	use codec::{Decode, Encode};
-	// The testing primitives are very useful for avoiding having to work with signatures
-	// or public keys. `u64` is used as the `AccountId` and no `Signature`s are requried.
-	use sr_primitives::{
-		BuildStorage, traits::{BlakeTwo256, IdentityLookup}, testing::{Digest, DigestItem, Header}
-	};
-	use balances;
+			assert_ok!(Claims::set_index_claimed(3));
+			assert_ok!(Claims::set_index_claimed(17));
+			assert_eq!(Claims::claimed_bits(), vec![0b0000_1000, 0, 0b0000_0010]);
+			assert!(Claims::is_index_claimed(3));
+			assert!(Claims::is_index_claimed(17));
+			assert!(!Claims::is_index_claimed(4));
+			assert!(!Claims::is_index_claimed(16));
 
-	impl_outer_origin! {
-		pub enum Origin for Test {}
+			assert_noop!(Claims::set_index_claimed(3), "Leaf index already claimed");
+			assert_noop!(Claims::set_index_claimed(17), "Leaf index already claimed");
+		});
 	}
 
-	// For testing the module, we construct most of a mock runtime. This means
-	// first constructing a configuration type (`Test`) which `impl`s each of the
-	// configuration traits of modules we want to use.
-	#[derive(Clone, Eq, PartialEq)]
-	pub struct Test;
-	impl system::Trait for Test {
-		type Origin = Origin;
-		type Index = u64;
-		type BlockNumber = u64;
-		type Hash = H256;
-		type Hashing = BlakeTwo256;
-		type Digest = Digest;
-		type AccountId = u64;
-		type Lookup = IdentityLookup<u64>;
-		type Header = Header;
-		type Event = ();
-		type Log = DigestItem;
-	}
-	impl balances::Trait for Test {
-		type Balance = u64;
-		type OnFreeBalanceZero = ();
-		type OnNewAccount = ();
-		type EnsureAccountLiquid = ();
-		type Event = ();
+	#[test]
+	fn claims_report_the_remaining_total() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(claims_events(), vec![
+				RawEvent::TotalUpdated(0),
+				RawEvent::AllClaimsCompleted,
+				RawEvent::Claimed(42, alice_eth(), 100, 100, 0, true),
+			]);
+		});
 	}
-	impl Trait for Test {
-		type Event = ();
+
+	#[test]
+	fn total_is_checked_at_the_end_of_each_block() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			Claims::on_finalise(1);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			Claims::on_finalise(2);
+		});
 	}
-	type Balances = balances::Module<Test>;
-	type Claims = Module<Test>;
 
-	fn alice_secret() -> secp256k1::SecretKey {
-		secp256k1::SecretKey::parse(&keccak256(b"Alice")).unwrap()
+	#[test]
+	fn total_check_counts_every_kind_of_claim() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			chunked_claims: vec![(bob_eth(), vec![100, 50])],
+			labeled_claims: vec![(charlie_eth(), vec![(b"team".to_vec(), 100), (b"bonus".to_vec(), 10)])],
+			foreign_claims: vec![([7; 20], 20)],
+			..Default::default()
+		}), || {
+			assert_eq!(Claims::addresses(), vec![alice_eth(), bob_eth(), charlie_eth(), [7; 20]]);
+			assert_ok!(Claims::import_child_claims(vec![([8; 20], 300)]));
+			Claims::on_finalise(1);
+
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_ok!(Claims::claim_label(Origin::signed(7), charlie_sig(&7u64.encode()), b"team".to_vec()));
+			Claims::on_finalise(2);
+		});
 	}
-	fn alice_public() -> secp256k1::PublicKey {
-		secp256k1::PublicKey::from_secret_key(&alice_secret())
+
+	#[test]
+	#[should_panic(expected = "Total has drifted from the sum of claims")]
+	fn drifting_total_of_child_claims_fails_the_check() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::import_child_claims(vec![(bob_eth(), 200)]));
+			runtime_io::clear_child_storage(CHILD_STORAGE_KEY, &bob_eth());
+			Claims::on_finalise(1);
+		});
 	}
-	fn alice_eth() -> EthereumAddress {
-		let mut res = EthereumAddress::default();
-		res.copy_from_slice(&keccak256(&alice_public().serialize()[1..65])[12..]);
-		res
+
+	#[test]
+	#[should_panic(expected = "Total has drifted from the sum of claims")]
+	fn drifting_total_fails_the_check() {
+		with_externalities(&mut new_test_ext(), || {
+			<Total<Test>>::put(99);
+			Claims::on_finalise(1);
+		});
 	}
-	fn alice_sig(who: &[u8]) -> EcdsaSignature {
-		let msg = keccak256(&create_msg(who));
-		let (sig, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&msg), &alice_secret()).unwrap();
-		let sig: ([u8; 32], [u8; 32]) = Decode::decode(&mut &sig.serialize()[..]).unwrap();
-		(sig.0, sig.1, recovery_id.serialize() as i8)
+
+	#[test]
+	fn claiming_last_allocation_completes_claims() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Claims::total(), 100);
+			assert!(!claims_events().contains(&RawEvent::AllClaimsCompleted));
+
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Claims::total(), 0);
+			assert!(claims_events().contains(&RawEvent::AllClaimsCompleted));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Balances::free_balance(&69), 200);
+		});
 	}
-	fn bob_secret() -> secp256k1::SecretKey {
-		secp256k1::SecretKey::parse(&keccak256(b"Bob")).unwrap()
+
+	#[test]
+	fn claims_can_be_paid_from_a_reserve() {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test>{
+			balances: vec![(5, 500)],
+			..Default::default()
+		}.build_storage().unwrap().0);
+		t.extend(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 150), (charlie_eth(), 300)],
+			pay_from_reserve: true,
+			reserve_account: 5,
+			..Default::default()
+		}.build_storage().unwrap().0);
+		with_externalities(&mut t.into(), || {
+			// Claims move funds out of the reserve rather than minting them.
+			assert_eq!(Balances::total_issuance(), 500);
+
+			// The reserve runs out at the third claim, so none of the batch is paid.
+			assert_noop!(
+				Claims::claim_batch(Origin::signed(1), vec![
+					(42, alice_sig(&42u64.encode())),
+					(70, charlie_sig(&70u64.encode())),
+					(69, bob_sig(&69u64.encode())),
+				], false),
+				"Claims reserve has too little to pay the claim"
+			);
+			assert_eq!(Balances::free_balance(&5), 500);
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_eq!(Balances::free_balance(&70), 0);
+			assert_eq!(Balances::total_issuance(), 500);
+
+			assert_ok!(Claims::claim_batch(Origin::signed(1), vec![
+				(42, alice_sig(&42u64.encode())),
+				(69, bob_sig(&69u64.encode())),
+			], false));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Balances::free_balance(&69), 150);
+			assert_eq!(Balances::free_balance(&5), 250);
+			assert_eq!(Balances::total_issuance(), 500);
+			assert_eq!(Claims::total(), 300);
+
+			assert_noop!(
+				Claims::claim(Origin::signed(70), charlie_sig(&70u64.encode())),
+				"Claims reserve has too little to pay the claim"
+			);
+			assert_eq!(Balances::free_balance(&5), 250);
+			assert_eq!(Balances::total_issuance(), 500);
+		});
 	}
-	fn bob_public() -> secp256k1::PublicKey {
-		secp256k1::PublicKey::from_secret_key(&bob_secret())
+
+	#[test]
+	fn the_reserve_covers_every_payout() {
+		let ext = |config: GenesisConfig<Test>| {
+			let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+			t.extend(balances::GenesisConfig::<Test>{
+				balances: vec![(5, 250)],
+				..Default::default()
+			}.build_storage().unwrap().0);
+			t.extend(GenesisConfig::<Test>{
+				claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+				pay_from_reserve: true,
+				reserve_account: 5,
+				..config
+			}.build_storage().unwrap().0);
+			t.into()
+		};
+
+		// Each claim of the batch is covered, but not both.
+		with_externalities(&mut ext(Default::default()), || {
+			assert_noop!(
+				Claims::claim_batch(Origin::signed(1), vec![
+					(42, alice_sig(&42u64.encode())),
+					(69, bob_sig(&69u64.encode())),
+				], false),
+				"Claims reserve has too little to pay the claim"
+			);
+			assert_eq!(Balances::free_balance(&5), 250);
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_eq!(Claims::total(), 300);
+		});
+
+		// The claim is covered, but not its bonus.
+		with_externalities(&mut ext(GenesisConfig::<Test>{
+			bonus_tiers: vec![(10, Perbill::from_billionths(500_000_000))],
+			bonus_pool: 1000,
+			..Default::default()
+		}), || {
+			assert_noop!(
+				Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())),
+				"Claims reserve has too little to pay the claim"
+			);
+			assert_eq!(Balances::free_balance(&5), 250);
+
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 150);
+			assert_eq!(Balances::free_balance(&5), 100);
+		});
 	}
-	fn bob_eth() -> EthereumAddress {
-		let mut res = EthereumAddress::default();
-		res.copy_from_slice(&keccak256(&bob_public().serialize()[1..65])[12..]);
-		res
+
+	#[test]
+	fn claims_to_rich_accounts_are_rejected_or_reduced() {
+		let ext = |rate| {
+			let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+			t.extend(balances::GenesisConfig::<Test>{
+				balances: vec![(42, 1_000), (69, 500)],
+				..Default::default()
+			}.build_storage().unwrap().0);
+			t.extend(GenesisConfig::<Test>{
+				claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+				max_prior_balance_for_claim: 500,
+				rich_claim_rate: rate,
+				..Default::default()
+			}.build_storage().unwrap().0);
+			t
+		};
+
+		with_externalities(&mut ext(Perbill::zero()).into(), || {
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Account already holds too much to claim"
+			);
+			// At the threshold isn't over it.
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 700);
+		});
+
+		with_externalities(&mut ext(Perbill::from_billionths(250_000_000)).into(), || {
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 1_025);
+			assert_eq!(Claims::claims(&alice_eth()), None);
+			assert_eq!(Claims::total(), 200);
+			assert_eq!(
+				claims_events().last(),
+				Some(&RawEvent::Claimed(42, alice_eth(), 25, 25, 0, false))
+			);
+		});
+
+		// The earlier claims of a batch count towards what its destination holds.
+		let batch = || vec![
+			(69, bob_sig(&69u64.encode())),
+			(69, alice_sig(&69u64.encode())),
+		];
+		with_externalities(&mut ext(Perbill::zero()).into(), || {
+			assert_noop!(
+				Claims::claim_batch(Origin::signed(1), batch(), false),
+				"Account already holds too much to claim"
+			);
+			assert_eq!(Balances::free_balance(&69), 500);
+			assert_eq!(Claims::total(), 300);
+		});
+
+		with_externalities(&mut ext(Perbill::from_billionths(250_000_000)).into(), || {
+			assert_ok!(Claims::claim_batch(Origin::signed(1), batch(), false));
+			assert_eq!(Balances::free_balance(&69), 725);
+			assert_eq!(Claims::total(), 0);
+		});
 	}
-	fn bob_sig(who: &[u8]) -> EcdsaSignature {
-		let msg = keccak256(&create_msg(who));
-		let (sig, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&msg), &bob_secret()).unwrap();
-		let sig: ([u8; 32], [u8; 32]) = Decode::decode(&mut &sig.serialize()[..]).unwrap();
-		(sig.0, sig.1, recovery_id.serialize() as i8)
+
+	#[test]
+	fn claims_below_existential_deposit_are_topped_up() {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test>{
+			existential_deposit: 150,
+			..Default::default()
+		}.build_storage().unwrap().0);
+		t.extend(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 120)],
+			top_up_to_ed: true,
+			top_up_pot: 60,
+			..Default::default()
+		}.build_storage().unwrap().0);
+		with_externalities(&mut t.into(), || {
+			// The pot is kept out of `Total`.
+			assert_eq!(Claims::total(), 220);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 150);
+			assert_eq!(Claims::top_up_pot(), 10);
+			assert_eq!(Claims::total(), 120);
+
+			// The pot can't cover the next top-up, so the claim is credited as it is.
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 120);
+			assert_eq!(Claims::top_up_pot(), 10);
+			assert_eq!(Claims::total(), 0);
+		});
 	}
 
-	// This function basically just builds a genesis storage key/value store according to
-	// our desired mockup.
-	fn new_test_ext() -> sr_io::TestExternalities<Blake2Hasher> {
+	#[test]
+	fn batch_claims_below_existential_deposit_are_merged() {
 		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
-		// We use default for brevity, but you can configure as desired if needed.
-		t.extend(balances::GenesisConfig::<Test>::default().build_storage().unwrap().0);
+		t.extend(balances::GenesisConfig::<Test>{
+			existential_deposit: 150,
+			..Default::default()
+		}.build_storage().unwrap().0);
 		t.extend(GenesisConfig::<Test>{
-			claims: vec![(alice_eth(), 100)],
+			claims: vec![(alice_eth(), 60), (bob_eth(), 60), (charlie_eth(), 60)],
+			merge_batch_credits: true,
+			..Default::default()
 		}.build_storage().unwrap().0);
-		t.into()
+		with_externalities(&mut t.into(), || {
+			assert_ok!(Claims::claim_batch(Origin::signed(1), vec![
+				(42, alice_sig(&42u64.encode())),
+				(42, bob_sig(&42u64.encode())),
+				(42, charlie_sig(&42u64.encode())),
+			], false));
+			assert_eq!(Balances::free_balance(&42), 180);
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(
+				claims_events().into_iter().filter(|e| match e {
+					RawEvent::Claimed(..) => true,
+					_ => false,
+				}).collect::<Vec<_>>(),
+				vec![
+					RawEvent::Claimed(42, alice_eth(), 60, 60, 0, true),
+					RawEvent::Claimed(42, bob_eth(), 60, 60, 0, true),
+					RawEvent::Claimed(42, charlie_eth(), 60, 60, 0, true),
+				]
+			);
+		});
 	}
 
 	#[test]
-	fn basic_setup_works() {
-		with_externalities(&mut new_test_ext(), || {
+	fn storage_can_be_migrated_from_an_old_prefix() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![],
+			..Default::default()
+		}), || {
+			// What the module stored when its storage was declared `as OldClaims`.
+			let put = |key: &[u8], value: Vec<u8>|
+				runtime_io::set_storage(&runtime_io::twox_128(key), &value);
+			put(b"OldClaims Total", 100u64.encode());
+			put(b"OldClaims AddressCount", 1u32.encode());
+			let mut key = b"OldClaims AddressAt".to_vec();
+			0u32.encode_to(&mut key);
+			put(&key, alice_eth().encode());
+			let mut key = b"OldClaims AddressIndex".to_vec();
+			alice_eth().encode_to(&mut key);
+			put(&key, 0u32.encode());
+			let mut key = b"OldClaims Claims".to_vec();
+			alice_eth().encode_to(&mut key);
+			put(&key, 100u64.encode());
+			let mut key = b"OldClaims ClaimedAccounts".to_vec();
+			7u64.encode_to(&mut key);
+			put(&key, true.encode());
+			assert_eq!(Claims::total(), 0);
+			assert_eq!(Claims::claims(&alice_eth()), None);
+
+			assert_noop!(
+				Claims::migrate_storage_prefix(b"Claims".to_vec(), vec![]),
+				"Storage is already under this prefix"
+			);
+			assert_ok!(Claims::migrate_storage_prefix(b"OldClaims".to_vec(), vec![7]));
 			assert_eq!(Claims::total(), 100);
+			assert_eq!(Claims::addresses(), vec![alice_eth()]);
+			assert_eq!(Claims::address_index(&alice_eth()), Some(0));
 			assert_eq!(Claims::claims(&alice_eth()), Some(100));
-			assert_eq!(Claims::claims(&[0; 20]), None);
+			assert!(Claims::has_claimed(&7));
+			assert_eq!(runtime_io::storage(&runtime_io::twox_128(b"OldClaims Total")), None);
+
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
 		});
 	}
 
 	#[test]
-	fn claiming_works() {
+	fn prevalidator_rejects_claims_bound_to_fail() {
 		with_externalities(&mut new_test_ext(), || {
-			assert_eq!(Balances::free_balance(&42), 0);
+			let validate = |who: u64, call: Call<Test>| ClaimPrevalidator::<Test>::validate(&who, &call);
+			assert_eq!(validate(42, Call::claim(alice_sig(&42u64.encode()))), Ok(()));
+			assert_eq!(
+				validate(42, Call::claim(([0; 32], [0; 32], RecoveryId::default()))),
+				Err("Invalid Ethereum signature")
+			);
+			let no_claim = Err("Ethereum address has no claim");
+			assert_eq!(validate(42, Call::claim(bob_sig(&42u64.encode()))), no_claim);
+			// Signed for another account, so another address is recovered.
+			assert_eq!(validate(69, Call::claim(alice_sig(&42u64.encode()))), no_claim);
+			// Other calls are left to dispatch.
+			assert_eq!(validate(42, Call::unlock_vested()), Ok(()));
+
 			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
-			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(validate(42, Call::claim(alice_sig(&42u64.encode()))), no_claim);
 		});
 	}
 
 	#[test]
-	fn double_claiming_doesnt_work() {
-		with_externalities(&mut new_test_ext(), || {
-			assert_eq!(Balances::free_balance(&42), 0);
-			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
-			assert_noop!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())), "Ethereum address has no claim");
+	fn prevalidator_checks_every_kind_of_claim() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100)],
+			chunked_claims: vec![(bob_eth(), vec![100, 50])],
+			labeled_claims: vec![(charlie_eth(), vec![(b"team".to_vec(), 100)])],
+			..Default::default()
+		}), || {
+			let validate = |who: u64, call: Call<Test>| ClaimPrevalidator::<Test>::validate(&who, &call);
+			let no_claim = Err("Ethereum address has no claim");
+
+			let batch = |sigs: Vec<(u64, EcdsaSignature)>| Call::claim_batch(sigs, false);
+			assert_eq!(validate(1, batch(vec![(42, alice_sig(&42u64.encode()))])), Ok(()));
+			assert_eq!(
+				validate(1, batch(vec![(42, alice_sig(&42u64.encode())), (69, alice_sig(&42u64.encode()))])),
+				no_claim
+			);
+
+			let no_chunk = Err("Ethereum address has no chunk left to claim");
+			assert_eq!(validate(69, Call::claim_next_chunk(bob_sig(&69u64.encode()))), Ok(()));
+			assert_eq!(validate(69, Call::claim_next_chunk(alice_sig(&69u64.encode()))), no_chunk);
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_ok!(Claims::claim_next_chunk(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(validate(69, Call::claim_next_chunk(bob_sig(&69u64.encode()))), no_chunk);
+
+			let label = |sig: EcdsaSignature, label: &[u8]| Call::claim_label(sig, label.to_vec());
+			assert_eq!(validate(7, label(charlie_sig(&7u64.encode()), b"team")), Ok(()));
+			assert_eq!(
+				validate(7, label(charlie_sig(&7u64.encode()), b"bonus")),
+				Err("Ethereum address has no claim under the label")
+			);
+
+			let address = b"BTRjp7Wgjtph9sH".to_vec();
+			let digest = |sig: EcdsaSignature| Call::claim_ss58_digest(address.clone(), sig);
+			assert_eq!(validate(42, digest(sign_message(&alice_secret(), &address))), Ok(()));
+			assert_eq!(validate(42, digest(sign_message(&alice_secret(), b"BUwihGSfZoDNCCb"))), no_claim);
+			assert_eq!(
+				validate(69, digest(sign_message(&alice_secret(), &address))),
+				Err("SS58 address is not that of the sender")
+			);
 		});
 	}
 
 	#[test]
-	fn non_sender_sig_doesnt_work() {
-		with_externalities(&mut new_test_ext(), || {
-			assert_eq!(Balances::free_balance(&42), 0);
-			assert_noop!(Claims::claim(Origin::signed(42), alice_sig(&69u64.encode())), "Ethereum address has no claim");
+	fn claiming_through_inherent_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(alice_eth(), 100), (bob_eth(), 200)],
+			..Default::default()
+		}), || {
+			assert!(<Claims as ProvideInherent>::create_inherent(&InherentData::new()).is_none());
+
+			let mut data = InherentData::new();
+			let claims: InherentType<u64> = vec![
+				(42, alice_sig(&42u64.encode())),
+				(69, bob_sig(&69u64.encode())),
+			];
+			data.put_data(INHERENT_IDENTIFIER, &claims).unwrap();
+			let call = <Claims as ProvideInherent>::create_inherent(&data).unwrap();
+
+			assert!(Claims::dispatch(call.clone(), Origin::signed(1)).is_err());
+			assert_ok!(Claims::dispatch(call, Origin::INHERENT));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Balances::free_balance(&69), 200);
+			assert_eq!(Claims::total(), 0);
 		});
 	}
 
 	#[test]
-	fn non_claimant_doesnt_work() {
+	fn claiming_through_inherent_is_all_or_nothing() {
 		with_externalities(&mut new_test_ext(), || {
-			assert_eq!(Balances::free_balance(&42), 0);
-			assert_noop!(Claims::claim(Origin::signed(42), bob_sig(&69u64.encode())), "Ethereum address has no claim");
+			let claims = vec![
+				(42, alice_sig(&42u64.encode())),
+				(69, bob_sig(&69u64.encode())),
+			];
+			assert_noop!(
+				Claims::dispatch(Call::migrate_claims(claims), Origin::INHERENT),
+				"Ethereum address has no claim"
+			);
+
+			let claims = vec![
+				(42, alice_sig(&42u64.encode())),
+				(42, alice_sig(&42u64.encode())),
+			];
+			assert_noop!(
+				Claims::dispatch(Call::migrate_claims(claims), Origin::INHERENT),
+				"Ethereum address claimed twice in batch"
+			);
 		});
 	}
 
@@ -272,7 +6456,21 @@ mod tests {
 		let sig = EcdsaSignature::decode(&mut &sig[..]).unwrap();
 		let who = 42u64.encode();
 		let msg = create_msg(&who);
-		let signer = eth_recover(&sig, &who).unwrap();
+		let signer = eth_recover_message::<()>(&sig, &msg).unwrap();
 		assert_eq!(signer, hex!["DF67EC7EAe23D2459694685257b6FC59d1BAA1FE"]);
 	}
+
+	#[test]
+	fn real_eth_sig_claiming_works() {
+		with_externalities(&mut new_test_ext_with(GenesisConfig::<Test>{
+			claims: vec![(hex!["DF67EC7EAe23D2459694685257b6FC59d1BAA1FE"], 100)],
+			..Default::default()
+		}), || {
+			let sig = hex!["7505f2880114da51b3f5d535f8687953c0ab9af4ab81e592eaebebf53b728d2b6dfd9b5bcd70fee412b1f31360e7c2774009305cb84fc50c1d0ff8034dfa5fff1c"];
+			let sig = EcdsaSignature::decode(&mut &sig[..]).unwrap();
+			assert_ok!(Claims::claim(Origin::signed(42), sig));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_eq!(Claims::total(), 0);
+		});
+	}
 }
@@ -33,6 +33,9 @@ extern crate secp256k1;
 #[macro_use]
 extern crate parity_codec_derive;
 extern crate parity_codec as codec;
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate serde_derive;
 
 extern crate substrate_consensus_aura_primitives as consensus_aura;
 extern crate substrate_primitives;
@@ -71,13 +74,13 @@ extern crate polkadot_primitives as primitives;
 extern crate substrate_keyring as keyring;
 
 mod parachains;
-mod claims;
+pub mod claims;
 
 use rstd::prelude::*;
 use substrate_primitives::u32_trait::{_2, _4};
 use primitives::{
 	AccountId, AccountIndex, Balance, BlockNumber, Hash, Nonce, SessionKey, Signature,
-	parachain,
+	parachain, claims as claims_primitives,
 };
 use client::{
 	block_builder::api::{self as block_builder_api, InherentData, CheckInherentsResult},
@@ -85,7 +88,7 @@ use client::{
 };
 use sr_primitives::{
 	ApplyResult, generic, transaction_validity::TransactionValidity,
-	traits::{Convert, BlakeTwo256, Block as BlockT, DigestFor, StaticLookup}
+	traits::{Convert, BlakeTwo256, Block as BlockT, DigestFor, StaticLookup, Zero}
 };
 use version::RuntimeVersion;
 use grandpa::fg_primitives::{self, ScheduledChange};
@@ -102,6 +105,7 @@ pub use consensus::Call as ConsensusCall;
 pub use timestamp::Call as TimestampCall;
 pub use balances::Call as BalancesCall;
 pub use parachains::{Call as ParachainsCall, INHERENT_IDENTIFIER as PARACHAIN_INHERENT_IDENTIFIER};
+pub use claims::INHERENT_IDENTIFIER as CLAIMS_INHERENT_IDENTIFIER;
 pub use sr_primitives::{Permill, Perbill};
 pub use timestamp::BlockPeriod;
 pub use srml_support::{StorageValue, RuntimeMetadata};
@@ -235,6 +239,16 @@ impl sudo::Trait for Runtime {
 
 impl claims::Trait for Runtime {
 	type Event = Event;
+	type CoSignature = Signature;
+	type Currency = Balances;
+	type Processor = (claims::FreezeCheck<Runtime>, claims::AmountBounds<Runtime>);
+	type ForeignRate = ();
+	type TransactionFee = Balances;
+	type AddressDerivation = ();
+	type PreHashing = claims::Blake2PreHashing;
+	type Identity = ();
+	type Teleport = ();
+	type WebAuthn = ();
 }
 
 construct_runtime!(
@@ -262,7 +276,7 @@ construct_runtime!(
 		Parachains: parachains::{Module, Call, Storage, Config<T>, Inherent},
 		Sudo: sudo,
 		UpgradeKey: upgrade_key,
-		Claims: claims,
+		Claims: claims::{Module, Call, Storage, Config<T>, Event<T>, Inherent},
 	}
 );
 
@@ -281,7 +295,18 @@ pub type UncheckedExtrinsic = generic::UncheckedMortalCompactExtrinsic<Address,
 /// Extrinsic type that has already been checked.
 pub type CheckedExtrinsic = generic::CheckedExtrinsic<AccountId, Nonce, Call>;
 /// Executive: handles dispatch to the various modules.
-pub type Executive = executive::Executive<Runtime, Block, system::ChainContext<Runtime>, Balances, AllModules>;
+/// Fees are paid through `claims::FeeFromClaim`, so accounts with no balance can claim.
+pub type Executive = executive::Executive<
+	Runtime, Block, system::ChainContext<Runtime>, claims::FeeFromClaim<Runtime, Balances>, AllModules
+>;
+
+/// The code transactions are rejected from the pool with when `claims::ClaimPrevalidator`
+/// finds them bound to fail.
+const INVALID_CLAIM: i8 = -30;
+
+/// The code transactions are rejected from the pool with when they are from an account with
+/// nothing to pay their fee with, and aren't a `claim` to pay it out of.
+const CANT_PAY_BUT_CLAIM: i8 = -31;
 
 impl_runtime_apis! {
 	impl client_api::Core<Block> for Runtime {
@@ -332,6 +357,27 @@ impl_runtime_apis! {
 
 	impl client_api::TaggedTransactionQueue<Block> for Runtime {
 		fn validate_transaction(tx: <Block as BlockT>::Extrinsic) -> TransactionValidity {
+			// Claims which can only fail are dropped here, before they take up a block.
+			if let (Some(&(ref address, ..)), &Call::Claims(ref call)) = (tx.signature.as_ref(), &tx.function) {
+				let checked = Indices::lookup(address.clone())
+					.and_then(|who| claims::ClaimPrevalidator::<Runtime>::validate(&who, call));
+				if checked.is_err() {
+					return TransactionValidity::Invalid(INVALID_CLAIM);
+				}
+			}
+			// `claims::FeeFromClaim` lets accounts with no balance defer their fee, which only a
+			// `claim` pays, so nothing else of theirs is let in.
+			if let Some(&(ref address, ..)) = tx.signature.as_ref() {
+				let unfunded = Indices::lookup(address.clone())
+					.map_or(false, |who| Balances::free_balance(&who).is_zero());
+				let claiming = match tx.function {
+					Call::Claims(claims::Call::claim(..)) => true,
+					_ => false,
+				};
+				if unfunded && !claiming {
+					return TransactionValidity::Invalid(CANT_PAY_BUT_CLAIM);
+				}
+			}
 			Executive::validate_transaction(tx)
 		}
 	}
@@ -354,6 +400,13 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl claims_primitives::ClaimsApi<Block> for Runtime {
+		fn claims_metrics() -> claims_primitives::ClaimsMetrics {
+			let (outstanding, claims_count) = Claims::metrics();
+			claims_primitives::ClaimsMetrics { outstanding, claims_count }
+		}
+	}
+
 	impl fg_primitives::GrandpaApi<Block> for Runtime {
 		fn grandpa_pending_change(digest: &DigestFor<Block>)
 			-> Option<ScheduledChange<BlockNumber>>
@@ -132,6 +132,41 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 		}),
 		claims: Some(ClaimsConfig {
 			claims: vec![],
+			co_signers: vec![],
+			claim_shares: vec![],
+			share_pool: 0,
+			rounding: Default::default(),
+			rounding_unit: 0,
+			categories: vec![],
+			foreign_claims: vec![],
+			chunked_claims: vec![],
+			labeled_claims: vec![],
+			vesting: vec![],
+			recovery_accounts: vec![],
+			statement: vec![],
+			claims_start: 0,
+			claims_end: 0,
+			finalize_delay: 0,
+			block_bound_tolerance: 0,
+			local_para_id: 0,
+			max_failed_attempts: 0,
+			failed_attempts_window: 0,
+			referral_rate: Perbill::zero(),
+			withholding_rate: Perbill::zero(),
+			withholding_account: Default::default(),
+			pay_from_reserve: false,
+			reserve_account: Default::default(),
+			keep_history: true,
+			top_up_to_ed: false,
+			top_up_pot: 0,
+			merge_batch_credits: false,
+			bonus_tiers: vec![],
+			bonus_pool: 0,
+			min_claim: 0,
+			max_claim: 0,
+			max_per_account: 0,
+			max_prior_balance_for_claim: 0,
+			rich_claim_rate: Perbill::zero(),
 		}),
 	}
 }
@@ -235,6 +270,41 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>, upgrade_key: H256) ->
 		}),
 		claims: Some(ClaimsConfig {
 			claims: vec![],
+			co_signers: vec![],
+			claim_shares: vec![],
+			share_pool: 0,
+			rounding: Default::default(),
+			rounding_unit: 0,
+			categories: vec![],
+			foreign_claims: vec![],
+			chunked_claims: vec![],
+			labeled_claims: vec![],
+			vesting: vec![],
+			recovery_accounts: vec![],
+			statement: vec![],
+			claims_start: 0,
+			claims_end: 0,
+			finalize_delay: 0,
+			block_bound_tolerance: 0,
+			local_para_id: 0,
+			max_failed_attempts: 0,
+			failed_attempts_window: 0,
+			referral_rate: Perbill::zero(),
+			withholding_rate: Perbill::zero(),
+			withholding_account: Default::default(),
+			pay_from_reserve: false,
+			reserve_account: Default::default(),
+			keep_history: true,
+			top_up_to_ed: false,
+			top_up_pot: 0,
+			merge_batch_credits: false,
+			bonus_tiers: vec![],
+			bonus_pool: 0,
+			min_claim: 0,
+			max_claim: 0,
+			max_per_account: 0,
+			max_prior_balance_for_claim: 0,
+			rich_claim_rate: Perbill::zero(),
 		}),
 	}
 }